@@ -0,0 +1,244 @@
+use elasticsearch::{ FieldCapsParts, SearchParts };
+use serde::Deserialize;
+use serde_json::{ json, Value };
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+use crate::plan::is_text_field;
+
+#[derive(Debug, Clone)]
+pub struct TermsBucket {
+    pub key: String,
+    pub doc_count: u64,
+    pub sub_aggregations: HashMap<String, Aggregation>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RangeBucket {
+    pub key: String,
+    pub from: Option<f64>,
+    pub to: Option<f64>,
+    pub doc_count: u64,
+    pub sub_aggregations: HashMap<String, Aggregation>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatsAgg {
+    pub count: u64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub avg: Option<f64>,
+    pub sum: f64,
+}
+
+#[derive(Debug, Clone)]
+pub enum Aggregation {
+    Terms(Vec<TermsBucket>),
+    Range(Vec<RangeBucket>),
+    Stats(StatsAgg),
+    Metric(Option<f64>),
+    Other(Value),
+}
+
+fn bucket_key(bucket: &Value) -> String {
+    match (&bucket["key_as_string"], &bucket["key"]) {
+        (Value::String(key), _) | (_, Value::String(key)) => key.clone(),
+        (_, key) => key.to_string(),
+    }
+}
+
+fn parse_aggregation(kind: &str, value: &Value) -> Aggregation {
+    let buckets = value["buckets"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+    match kind {
+        "sterms" | "lterms" | "dterms" | "umterms" => Aggregation::Terms(buckets
+            .iter()
+            .map(|bucket| TermsBucket {
+                key: bucket_key(bucket),
+                doc_count: bucket["doc_count"].as_u64().unwrap_or_default(),
+                sub_aggregations: parse_aggregations(bucket),
+            })
+            .collect()),
+        "range" | "date_range" => Aggregation::Range(buckets
+            .iter()
+            .map(|bucket| RangeBucket {
+                key: bucket_key(bucket),
+                from: bucket["from"].as_f64(),
+                to: bucket["to"].as_f64(),
+                doc_count: bucket["doc_count"].as_u64().unwrap_or_default(),
+                sub_aggregations: parse_aggregations(bucket),
+            })
+            .collect()),
+        "stats" => match serde_json::from_value(value.clone()) {
+            Ok(stats) => Aggregation::Stats(stats),
+            Err(_) => Aggregation::Other(value.clone()),
+        },
+        "avg" | "sum" | "min" | "max" | "value_count" | "cardinality" => Aggregation::Metric(value["value"].as_f64()),
+        _ => Aggregation::Other(value.clone()),
+    }
+}
+
+// Relies on `typed_keys`, which makes Elasticsearch prefix every aggregation name with its type, e.g. "sterms#category".
+pub fn parse_aggregations(container: &Value) -> HashMap<String, Aggregation> {
+    let Some(fields) = container.as_object() else { return HashMap::new() };
+
+    fields
+        .iter()
+        .filter_map(|(key, value)| {
+            let (kind, name) = key.split_once('#')?;
+            Some((name.to_string(), parse_aggregation(kind, value)))
+        })
+        .collect()
+}
+
+impl ElSearch {
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn aggregate(&self, index_name: &str, body: &Value) -> Result<HashMap<String, Aggregation>, ElSearchError> {
+        let mut body = body.clone();
+        if let Some(fields) = body.as_object_mut() {
+            fields.entry("size").or_insert(Value::from(0));
+        }
+
+        let response = Call::new("aggregate", index_name)
+            .send_checked(Some(&body), self.client
+                .search(SearchParts::Index(&[index_name]))
+                .typed_keys(true)
+                .body(&body)
+                .send())
+            .await?;
+        let resp_body = response.json::<Value>().await?;
+
+        Ok(parse_aggregations(&resp_body["aggregations"]))
+    }
+
+    // Text fields are turned away before aggregating, using the field's type from field_caps.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn terms_aggregation(&self, index_name: &str, field: &str, size: i64) -> Result<Vec<(String, i64)>, ElSearchError> {
+        let keyword_field = format!("{}.keyword", field);
+        let response = Call::new("terms_aggregation", index_name)
+            .send_checked(None, self.client
+                .field_caps(FieldCapsParts::Index(&[index_name]))
+                .fields(&[field, &keyword_field])
+                .send())
+            .await?;
+        let field_caps = response.json::<Value>().await?;
+        if is_text_field(&field_caps, field) {
+            let hint = if field_caps["fields"][&keyword_field].is_object() {
+                format!("use its keyword sub-field {}", keyword_field)
+            } else {
+                "map it as keyword to aggregate on it".to_string()
+            };
+            return Err(ElSearchError::InvalidArgument(format!("{} is a text field and cannot be aggregated; {}", field, hint)));
+        }
+
+        let body = json!({
+            "aggs": {
+                "terms": {
+                    "terms": { "field": field, "size": size }
+                }
+            }
+        });
+        let mut aggregations = self.aggregate(index_name, &body).await?;
+
+        match aggregations.remove("terms") {
+            Some(Aggregation::Terms(buckets)) => Ok(buckets
+                .into_iter()
+                .map(|bucket| (bucket.key, bucket.doc_count as i64))
+                .collect()),
+            _ => Err(ElSearchError::InvalidResponse(format!("no terms buckets returned for field {}", field))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::{ mock_client, received };
+
+    #[test]
+    fn typed_keys_select_the_parser_and_are_stripped_from_the_name() {
+        let aggregations = parse_aggregations(&json!({
+            "sterms#brands": { "buckets": [{ "key": "acme", "doc_count": 3 }] },
+            "lterms#ratings": { "buckets": [{ "key": 5, "doc_count": 2 }] },
+            "stats#price": { "count": 2, "min": 1.0, "max": 3.0, "avg": 2.0, "sum": 4.0 },
+            "avg#avg_price": { "value": 2.0 },
+            "cardinality#distinct": { "value": 7 },
+            "percentiles#latency": { "values": { "50.0": 12.0 } },
+            "untyped": { "value": 1 }
+        }));
+
+        assert_eq!(aggregations.len(), 6, "keys without a type prefix are skipped");
+        assert!(matches!(&aggregations["brands"], Aggregation::Terms(buckets) if buckets[0].key == "acme" && buckets[0].doc_count == 3));
+        assert!(matches!(&aggregations["ratings"], Aggregation::Terms(buckets) if buckets[0].key == "5"));
+        assert!(matches!(&aggregations["price"], Aggregation::Stats(stats) if stats.count == 2 && stats.avg == Some(2.0)));
+        assert!(matches!(aggregations["avg_price"], Aggregation::Metric(Some(value)) if value == 2.0));
+        assert!(matches!(aggregations["distinct"], Aggregation::Metric(Some(value)) if value == 7.0));
+        assert!(matches!(&aggregations["latency"], Aggregation::Other(value) if value["values"]["50.0"] == 12.0));
+    }
+
+    #[test]
+    fn sub_aggregations_are_parsed_per_bucket() {
+        let aggregations = parse_aggregations(&json!({
+            "sterms#category": { "buckets": [
+                {
+                    "key": "Audio",
+                    "doc_count": 2,
+                    "range#price_bands": { "buckets": [
+                        { "key": "*-100.0", "to": 100.0, "doc_count": 1, "max#top": { "value": 80.0 } },
+                        { "key": "100.0-*", "from": 100.0, "doc_count": 1, "max#top": { "value": 250.0 } }
+                    ] }
+                }
+            ] }
+        }));
+
+        let Aggregation::Terms(categories) = &aggregations["category"] else { panic!("{:?}", aggregations) };
+        let Aggregation::Range(bands) = &categories[0].sub_aggregations["price_bands"] else { panic!("{:?}", categories[0]) };
+        assert_eq!(bands.iter().map(|band| (band.from, band.to)).collect::<Vec<_>>(), vec![(None, Some(100.0)), (Some(100.0), None)]);
+        assert!(matches!(bands[1].sub_aggregations["top"], Aggregation::Metric(Some(value)) if value == 250.0));
+    }
+
+    #[tokio::test]
+    async fn terms_aggregation_turns_away_text_fields_before_searching() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/products/_field_caps"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "fields": {
+                "name": { "text": { "type": "text", "searchable": true, "aggregatable": false } },
+                "name.keyword": { "keyword": { "type": "keyword", "searchable": true, "aggregatable": true } }
+            } })))
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server).terms_aggregation("products", "name", 10).await.unwrap_err();
+        assert!(matches!(&err, ElSearchError::InvalidArgument(reason) if reason.contains("name.keyword")), "{:?}", err);
+        assert!(received(&server).await.iter().all(|request| !request.url.path().ends_with("/_search")));
+    }
+
+    #[tokio::test]
+    async fn terms_aggregation_counts_keyword_fields() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/products/_field_caps"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "fields": {
+                "brand": { "keyword": { "type": "keyword", "searchable": true, "aggregatable": true } }
+            } })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "hits": { "hits": [] },
+                "aggregations": { "sterms#terms": { "buckets": [{ "key": "acme", "doc_count": 4 }, { "key": "globex", "doc_count": 1 }] } }
+            })))
+            .mount(&server)
+            .await;
+
+        let buckets = mock_client(&server).terms_aggregation("products", "brand", 10).await.unwrap();
+        assert_eq!(buckets, vec![("acme".to_string(), 4), ("globex".to_string(), 1)]);
+    }
+}
@@ -0,0 +1,66 @@
+use elasticsearch::{ http::{ response::Response, StatusCode }, indices::{ IndicesGetAliasParts, IndicesPutAliasParts } };
+use serde_json::{ json, Value };
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+
+impl ElSearch {
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn create_alias(&self, index_name: &str, alias: &str) -> Result<Response, ElSearchError> {
+        let call = Call::new("create_alias", index_name);
+        let response = call
+            .send(None, self.client
+                .indices()
+                .put_alias(IndicesPutAliasParts::IndexName(&[index_name], alias))
+                .send())
+            .await?;
+        if response.status_code() == StatusCode::NOT_FOUND {
+            return Err(ElSearchError::IndexNotFound(index_name.to_string()));
+        }
+        call.ensure_success(response).await
+    }
+
+    // Moves the alias in a single _aliases request, so readers never see it missing or on both indices.
+    #[instrument(skip_all)]
+    pub async fn swap_alias(&self, alias: &str, from_index: &str, to_index: &str) -> Result<Response, ElSearchError> {
+        let body = json!({
+            "actions": [
+                { "remove": { "index": from_index, "alias": alias } },
+                { "add": { "index": to_index, "alias": alias } }
+            ]
+        });
+
+        Call::new("swap_alias", to_index)
+            .send_checked(Some(&body), self.client
+                .indices()
+                .update_aliases()
+                .body(&body)
+                .send())
+            .await
+    }
+
+    // Returns the indices the alias points to, or an empty list if the alias does not exist.
+    #[instrument(skip_all)]
+    pub async fn resolve_alias(&self, alias: &str) -> Result<Vec<String>, ElSearchError> {
+        let call = Call::new("resolve_alias", alias);
+        let response = call
+            .send(None, self.client
+                .indices()
+                .get_alias(IndicesGetAliasParts::Name(&[alias]))
+                .send())
+            .await?;
+        if response.status_code() == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        let resp_body = call.ensure_success(response).await?.json::<Value>().await?;
+
+        let mut indices: Vec<String> = resp_body
+            .as_object()
+            .map(|indices| indices.keys().cloned().collect())
+            .unwrap_or_default();
+        indices.sort();
+        Ok(indices)
+    }
+}
@@ -0,0 +1,144 @@
+use elasticsearch::http::response::Response;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::future::Future;
+use std::panic;
+use std::thread;
+use tokio::runtime::{ Builder, Handle, Runtime, RuntimeFlavor };
+use tokio::task;
+
+use crate::bulk::BulkOperation;
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::search::SearchResult;
+
+// Synchronous wrapper around `ElSearch`, driving requests on a current-thread runtime
+// of its own. Its methods block the calling thread, so from async code they belong in
+// `spawn_blocking`; called on a runtime worker directly they still work but stall it.
+pub struct BlockingElSearch {
+    inner: ElSearch,
+    // Only `None` while dropping.
+    runtime: Option<Runtime>,
+}
+
+impl BlockingElSearch {
+    pub fn new(inner: ElSearch) -> Result<Self, ElSearchError> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(BlockingElSearch { inner, runtime: Some(runtime) })
+    }
+
+    pub fn from_env() -> Result<Self, ElSearchError> {
+        Self::new(ElSearch::from_env()?)
+    }
+
+    pub fn inner(&self) -> &ElSearch {
+        &self.inner
+    }
+
+    // `Runtime::block_on` panics on a thread that is already running async code, so inside
+    // a runtime the thread is handed off first. A multi-thread worker can do that with
+    // `block_in_place`; a current-thread runtime cannot, and because its own thread and its
+    // blocking threads look the same from here, the future runs on a scoped thread instead.
+    fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        let runtime = self.runtime.as_ref().expect("runtime is only taken on drop");
+        match Handle::try_current() {
+            Err(_) => runtime.block_on(future),
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                task::block_in_place(|| runtime.block_on(future))
+            }
+            Ok(_) => thread::scope(|scope| {
+                scope
+                    .spawn(|| runtime.block_on(future))
+                    .join()
+                    .unwrap_or_else(|payload| panic::resume_unwind(payload))
+            }),
+        }
+    }
+
+    pub fn search_typed<T: DeserializeOwned + Send>(&self, index_name: &str, body: &Value) -> Result<SearchResult<T>, ElSearchError> {
+        self.block_on(self.inner.search_typed(index_name, body))
+    }
+
+    pub fn add_document(&self, index_name: &str, body: &Value) -> Result<Response, ElSearchError> {
+        self.block_on(self.inner.add_document(index_name, body))
+    }
+
+    pub fn bulk(&self, index_name: &str, operations: Vec<BulkOperation>) -> Result<Response, ElSearchError> {
+        self.block_on(self.inner.bulk(index_name, operations))
+    }
+
+    pub fn create_index(&self, index_name: &str, body: &Value) -> Result<Response, ElSearchError> {
+        self.block_on(self.inner.create_index(index_name, body))
+    }
+
+    pub fn get_document<T: DeserializeOwned + Send>(&self, index_name: &str, id: &str) -> Result<Option<T>, ElSearchError> {
+        self.block_on(self.inner.get_document(index_name, id))
+    }
+}
+
+// Dropping a runtime blocks until its tasks finish, which panics inside another runtime.
+impl Drop for BlockingElSearch {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::mock_client;
+
+    async fn document_server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/products/_doc/a"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "_id": "a", "_seq_no": 0, "_primary_term": 1, "found": true, "_source": { "name": "Laptop" }
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn fetch(es: &BlockingElSearch) -> Value {
+        es.get_document::<Value>("products", "a").unwrap().unwrap()
+    }
+
+    #[test]
+    fn works_outside_of_any_runtime() {
+        let server = Runtime::new().unwrap().block_on(document_server());
+        let es = BlockingElSearch::new(mock_client(&server)).unwrap();
+        assert_eq!(fetch(&es)["name"], "Laptop");
+    }
+
+    #[tokio::test]
+    async fn works_from_spawn_blocking() {
+        let server = document_server().await;
+        let es = BlockingElSearch::new(mock_client(&server)).unwrap();
+        let doc = tokio::task::spawn_blocking(move || fetch(&es)).await.unwrap();
+        assert_eq!(doc["name"], "Laptop");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn does_not_panic_on_a_multi_thread_worker() {
+        let server = document_server().await;
+        let es = BlockingElSearch::new(mock_client(&server)).unwrap();
+        assert_eq!(fetch(&es)["name"], "Laptop");
+    }
+
+    #[tokio::test]
+    async fn does_not_panic_on_a_current_thread_runtime() {
+        let server = document_server().await;
+        let es = BlockingElSearch::new(mock_client(&server)).unwrap();
+        assert_eq!(fetch(&es)["name"], "Laptop");
+    }
+}
@@ -0,0 +1,469 @@
+use elasticsearch::{ http::{ request::JsonBody, response::Response, StatusCode }, BulkParts };
+use futures::stream::{ self, StreamExt };
+use serde_json::{ json, Value };
+use std::fmt;
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::{ read_json, ElSearchError };
+use crate::logging::Call;
+
+pub const DEFAULT_CHUNK_DOCS: usize = 500;
+pub const DEFAULT_CHUNK_BYTES: usize = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkLimits {
+    pub max_docs: usize,
+    pub max_bytes: usize,
+}
+
+impl Default for ChunkLimits {
+    fn default() -> Self {
+        ChunkLimits {
+            max_docs: DEFAULT_CHUNK_DOCS,
+            max_bytes: DEFAULT_CHUNK_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BulkOperation {
+    Index { id: Option<String>, doc: Value },
+    Create { id: Option<String>, doc: Value },
+    Update { id: String, doc: Value },
+    Delete { id: String },
+}
+
+pub type BulkOp = BulkOperation;
+
+impl BulkOperation {
+    pub fn into_lines(self) -> Vec<Value> {
+        fn metadata(id: Option<String>) -> Value {
+            match id {
+                Some(id) => json!({ "_id": id }),
+                None => json!({}),
+            }
+        }
+
+        match self {
+            BulkOperation::Index { id, doc } => vec![json!({ "index": metadata(id) }), doc],
+            BulkOperation::Create { id, doc } => vec![json!({ "create": metadata(id) }), doc],
+            BulkOperation::Update { id, doc } => vec![json!({ "update": { "_id": id } }), json!({ "doc": doc })],
+            BulkOperation::Delete { id } => vec![json!({ "delete": { "_id": id } })],
+        }
+    }
+    // Index and create without an id generate a new document every time they are sent.
+    fn has_id(&self) -> bool {
+        match self {
+            BulkOperation::Index { id, .. } | BulkOperation::Create { id, .. } => id.is_some(),
+            BulkOperation::Update { .. } | BulkOperation::Delete { .. } => true,
+        }
+    }
+}
+
+pub fn bulk_body(operations: Vec<BulkOperation>) -> Vec<JsonBody<Value>> {
+    operations
+        .into_iter()
+        .flat_map(BulkOperation::into_lines)
+        .map(JsonBody::new)
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct BulkItemError {
+    pub position: usize,
+    pub status: u16,
+    pub error_type: String,
+    pub reason: String,
+}
+
+// A whole chunk that could not be sent, e.g. because of a transport error.
+#[derive(Debug, Clone)]
+pub struct BulkChunkError {
+    pub offset: usize,
+    pub len: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BulkReport {
+    pub successful: usize,
+    pub failed: Vec<BulkItemError>,
+    pub failed_chunks: Vec<BulkChunkError>,
+}
+
+impl BulkReport {
+    pub fn from_response_body(resp_body: &Value, offset: usize) -> Result<Self, ElSearchError> {
+        let items = resp_body["items"]
+            .as_array()
+            .ok_or_else(|| ElSearchError::InvalidResponse("bulk response has no items array".to_string()))?;
+
+        let mut report = BulkReport::default();
+        for (position, item) in items.iter().enumerate() {
+            let Some(result) = item.as_object().and_then(|actions| actions.values().next()) else {
+                return Err(ElSearchError::InvalidResponse(format!("bulk item {} has no action", offset + position)));
+            };
+
+            let status = result["status"].as_u64().unwrap_or_default() as u16;
+            if result.get("error").is_none() && (200..300).contains(&status) {
+                report.successful += 1;
+            } else {
+                report.failed.push(BulkItemError {
+                    position: offset + position,
+                    status,
+                    error_type: result["error"]["type"].as_str().unwrap_or("unknown").to_string(),
+                    reason: result["error"]["reason"].as_str().unwrap_or("unknown error").to_string(),
+                });
+            }
+        }
+        Ok(report)
+    }
+
+    pub fn merge(&mut self, other: BulkReport) {
+        self.successful += other.successful;
+        self.failed.extend(other.failed);
+        self.failed_chunks.extend(other.failed_chunks);
+    }
+
+    pub fn has_failures(&self) -> bool {
+        !self.failed.is_empty() || !self.failed_chunks.is_empty()
+    }
+}
+
+impl fmt::Display for BulkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Bulk indexing: {} succeeded, {} failed", self.successful, self.failed.len())?;
+        for item in &self.failed {
+            writeln!(f, "  #{} [{}] {}: {}", item.position, item.status, item.error_type, item.reason)?;
+        }
+        for chunk in &self.failed_chunks {
+            writeln!(f, "  chunk #{}..#{} not sent: {}", chunk.offset, chunk.offset + chunk.len, chunk.reason)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn split_into_chunks(operations: Vec<Value>, limits: ChunkLimits) -> Vec<Vec<Value>> {
+    let max_docs = limits.max_docs.max(1);
+    let mut chunks = Vec::new();
+    let mut chunk = Vec::new();
+    let mut chunk_bytes = 0;
+
+    for operation in operations {
+        let operation_bytes = serde_json::to_vec(&operation).map(|bytes| bytes.len()).unwrap_or_default();
+        if !chunk.is_empty() && (chunk.len() >= max_docs || chunk_bytes + operation_bytes > limits.max_bytes) {
+            chunks.push(std::mem::take(&mut chunk));
+            chunk_bytes = 0;
+        }
+        chunk_bytes += operation_bytes;
+        chunk.push(operation);
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+impl ElSearch {
+    // The documents are already stored at this point, so a failed refresh only delays when
+    // they become searchable and must not hide the report of what was indexed.
+    pub(crate) async fn refresh_after_import(&self, index_name: &str) {
+        if let Err(err) = self.refresh_index(index_name).await {
+            tracing::warn!(op = "refresh_index", index = index_name, error = %err, "refresh after import failed, documents become searchable at the next scheduled refresh");
+        }
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn bulk(&self, index_name: &str, operations: Vec<BulkOperation>) -> Result<Response, ElSearchError> {
+        tracing::debug!(op = "bulk", index = index_name, operations = operations.len(), "sending bulk request");
+        let logged_body = self.log_request_bodies
+            .then(|| Value::from(operations.iter().cloned().flat_map(BulkOperation::into_lines).collect::<Vec<_>>()));
+        let call = Call::new("bulk", index_name);
+        let send = || self.client
+            .bulk(BulkParts::Index(index_name))
+            .body(bulk_body(operations.clone()))
+            .send();
+        if operations.iter().all(BulkOperation::has_id) {
+            self.send_with_retry(call, logged_body.as_ref(), send).await
+        } else {
+            self.send_non_idempotent_with_retry(call, logged_body.as_ref(), send).await
+        }
+    }
+
+    async fn bulk_create_report(&self, index_name: &str, documents: Vec<Value>, offset: usize) -> Result<BulkReport, ElSearchError> {
+        let operations = documents
+            .into_iter()
+            .map(|doc| BulkOperation::Create { id: None, doc })
+            .collect();
+        self.bulk_report(index_name, operations, offset).await
+    }
+
+    // Items rejected with 429 are resubmitted on their own, following the
+    // client's retry policy, instead of replaying the whole batch.
+    async fn bulk_report(&self, index_name: &str, operations: Vec<BulkOperation>, offset: usize) -> Result<BulkReport, ElSearchError> {
+        let mut pending: Vec<(usize, BulkOperation)> = operations
+            .into_iter()
+            .enumerate()
+            .map(|(position, operation)| (offset + position, operation))
+            .collect();
+        let mut report = BulkReport::default();
+        let mut retries = 0;
+
+        loop {
+            let (positions, operations): (Vec<usize>, Vec<BulkOperation>) = pending.into_iter().unzip();
+            let resp_body = read_json::<Value>(self.bulk(index_name, operations.clone()).await?).await?;
+            let attempt = BulkReport::from_response_body(&resp_body, 0)?;
+            let exhausted = retries + 1 >= self.retry_policy.max_attempts;
+
+            report.successful += attempt.successful;
+            pending = Vec::new();
+            for mut item in attempt.failed {
+                let (Some(&position), Some(operation)) = (positions.get(item.position), operations.get(item.position)) else {
+                    return Err(ElSearchError::InvalidResponse(format!("bulk response has an item at position {} but only {} operations were sent", item.position, operations.len())));
+                };
+                if item.status == StatusCode::TOO_MANY_REQUESTS.as_u16() && !exhausted {
+                    pending.push((position, operation.clone()));
+                } else {
+                    item.position = position;
+                    report.failed.push(item);
+                }
+            }
+
+            if pending.is_empty() {
+                report.failed.sort_by_key(|item| item.position);
+                return Ok(report);
+            }
+            retries += 1;
+            tokio::time::sleep(self.retry_policy.backoff(retries)).await;
+        }
+    }
+
+    // Like `bulk`, but returns the per-item outcome and resubmits items rejected with 429.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn bulk_checked(&self, index_name: &str, operations: Vec<BulkOperation>) -> Result<BulkReport, ElSearchError> {
+        self.bulk_report(index_name, operations, 0).await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn bulk_create_checked(&self, index_name: &str, operations: Vec<Value>) -> Result<BulkReport, ElSearchError> {
+        self.bulk_create_report(index_name, operations, 0).await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn bulk_create_and_refresh(&self, index_name: &str, operations: Vec<Value>) -> Result<BulkReport, ElSearchError> {
+        let report = self.bulk_create_checked(index_name, operations).await?;
+        self.refresh_after_import(index_name).await;
+        Ok(report)
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn bulk_create_chunked(&self, index_name: &str, operations: Vec<Value>, chunk_size: usize) -> Result<BulkReport, ElSearchError> {
+        let limits = ChunkLimits {
+            max_docs: chunk_size,
+            ..ChunkLimits::default()
+        };
+        self.bulk_create_chunked_with(index_name, operations, limits).await
+    }
+
+    // A chunk that fails as a whole is recorded in `failed_chunks` and the remaining chunks
+    // are still sent. The index is refreshed once at the end; a failed refresh is only logged.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn bulk_create_chunked_with(&self, index_name: &str, operations: Vec<Value>, limits: ChunkLimits) -> Result<BulkReport, ElSearchError> {
+        let mut report = BulkReport::default();
+        let mut offset = 0;
+
+        for chunk in split_into_chunks(operations, limits) {
+            let chunk_len = chunk.len();
+            match self.bulk_create_report(index_name, chunk, offset).await {
+                Ok(chunk_report) => report.merge(chunk_report),
+                Err(err) => report.failed_chunks.push(BulkChunkError {
+                    offset,
+                    len: chunk_len,
+                    reason: err.to_string(),
+                }),
+            }
+            offset += chunk_len;
+        }
+
+        self.refresh_after_import(index_name).await;
+        Ok(report)
+    }
+
+    // Sends up to `concurrency` chunks at a time. The first chunk that fails at the
+    // transport level (after retries) aborts the import and drops the requests still in flight.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn bulk_create_concurrent(&self, index_name: &str, operations: Vec<Value>, chunk_size: usize, concurrency: usize) -> Result<BulkReport, ElSearchError> {
+        let limits = ChunkLimits {
+            max_docs: chunk_size,
+            ..ChunkLimits::default()
+        };
+
+        let mut offset = 0;
+        let chunks: Vec<(usize, Vec<Value>)> = split_into_chunks(operations, limits)
+            .into_iter()
+            .map(|chunk| {
+                let chunk_offset = offset;
+                offset += chunk.len();
+                (chunk_offset, chunk)
+            })
+            .collect();
+
+        let mut chunk_reports = stream::iter(chunks)
+            .map(|(chunk_offset, chunk)| self.bulk_create_report(index_name, chunk, chunk_offset))
+            .buffer_unordered(concurrency.max(1));
+
+        let mut report = BulkReport::default();
+        while let Some(chunk_report) = chunk_reports.next().await {
+            report.merge(chunk_report?);
+        }
+        report.failed.sort_by_key(|item| item.position);
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{ Duration, Instant };
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, Request, Respond, ResponseTemplate };
+
+    use super::*;
+    use crate::fixtures::{ bulk_response, ItemOutcome };
+    use crate::test_support::{ mock_client, ndjson_lines, received };
+
+    #[test]
+    fn each_operation_becomes_its_action_and_source_lines() {
+        let cases = vec![
+            (BulkOperation::Index { id: Some("a".to_string()), doc: json!({ "n": 1 }) }, vec![json!({ "index": { "_id": "a" } }), json!({ "n": 1 })]),
+            (BulkOperation::Index { id: None, doc: json!({ "n": 2 }) }, vec![json!({ "index": {} }), json!({ "n": 2 })]),
+            (BulkOperation::Create { id: Some("b".to_string()), doc: json!({ "n": 3 }) }, vec![json!({ "create": { "_id": "b" } }), json!({ "n": 3 })]),
+            (BulkOperation::Create { id: None, doc: json!({ "n": 4 }) }, vec![json!({ "create": {} }), json!({ "n": 4 })]),
+            (BulkOperation::Update { id: "c".to_string(), doc: json!({ "n": 5 }) }, vec![json!({ "update": { "_id": "c" } }), json!({ "doc": { "n": 5 } })]),
+            (BulkOperation::Delete { id: "d".to_string() }, vec![json!({ "delete": { "_id": "d" } })]),
+        ];
+        for (operation, lines) in cases {
+            assert_eq!(operation.into_lines(), lines);
+        }
+    }
+
+    #[tokio::test]
+    async fn bulk_sends_the_lines_of_every_operation_in_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_bulk"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(bulk_response(vec![
+                ItemOutcome::Created,
+                ItemOutcome::Updated,
+                ItemOutcome::Updated,
+                ItemOutcome::Deleted,
+            ])))
+            .mount(&server)
+            .await;
+
+        let operations = vec![
+            BulkOperation::Create { id: None, doc: json!({ "n": 1 }) },
+            BulkOperation::Index { id: Some("a".to_string()), doc: json!({ "n": 2 }) },
+            BulkOperation::Update { id: "a".to_string(), doc: json!({ "n": 3 }) },
+            BulkOperation::Delete { id: "a".to_string() },
+        ];
+        mock_client(&server).bulk("products", operations).await.unwrap();
+
+        let requests = received(&server).await;
+        assert_eq!(requests.len(), 1);
+        assert_eq!(ndjson_lines(&requests[0]), vec![
+            json!({ "create": {} }),
+            json!({ "n": 1 }),
+            json!({ "index": { "_id": "a" } }),
+            json!({ "n": 2 }),
+            json!({ "update": { "_id": "a" } }),
+            json!({ "doc": { "n": 3 } }),
+            json!({ "delete": { "_id": "a" } }),
+        ]);
+    }
+
+    // Acknowledges every document of a bulk request after a fixed delay, standing in for a
+    // cluster where each bulk request takes a while regardless of load.
+    struct SlowBulk(Duration);
+
+    impl Respond for SlowBulk {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let documents = ndjson_lines(request).len() / 2;
+            ResponseTemplate::new(200)
+                .set_body_json(bulk_response(vec![ItemOutcome::Created; documents]))
+                .set_delay(self.0)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_chunks_finish_sooner_than_sequential_ones() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_bulk"))
+            .respond_with(SlowBulk(Duration::from_millis(200)))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/products/_refresh"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .mount(&server)
+            .await;
+
+        let es = mock_client(&server);
+        let documents = (0..40).map(|n| json!({ "n": n })).collect::<Vec<_>>();
+
+        let started = Instant::now();
+        let sequential = es.bulk_create_chunked("products", documents.clone(), 10).await.unwrap();
+        let sequential_elapsed = started.elapsed();
+
+        let started = Instant::now();
+        let concurrent = es.bulk_create_concurrent("products", documents, 10, 4).await.unwrap();
+        let concurrent_elapsed = started.elapsed();
+
+        assert_eq!(sequential.successful, 40);
+        assert_eq!(concurrent.successful, 40);
+        // Four chunks of 200ms each: roughly 800ms one after another against 200ms side by side.
+        assert!(sequential_elapsed >= Duration::from_millis(800), "{:?}", sequential_elapsed);
+        assert!(concurrent_elapsed * 2 < sequential_elapsed, "concurrent {:?}, sequential {:?}", concurrent_elapsed, sequential_elapsed);
+    }
+
+    #[tokio::test]
+    async fn failed_refresh_keeps_the_report() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_bulk"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(bulk_response(vec![ItemOutcome::Created, ItemOutcome::Created])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/products/_refresh"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("refresh failed"))
+            .mount(&server)
+            .await;
+
+        let es = mock_client(&server);
+        let documents = vec![json!({ "n": 0 }), json!({ "n": 1 })];
+        let report = es.bulk_create_chunked("products", documents.clone(), 2).await.unwrap();
+        assert_eq!(report.successful, 2);
+        assert!(report.failed_chunks.is_empty());
+
+        let report = es.bulk_create_and_refresh("products", documents).await.unwrap();
+        assert_eq!(report.successful, 2);
+        assert!(received(&server).await.iter().any(|request| request.url.path() == "/products/_refresh"));
+    }
+
+    #[tokio::test]
+    async fn more_items_than_operations_is_an_invalid_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_bulk"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(bulk_response(vec![
+                ItemOutcome::Created,
+                ItemOutcome::failed(400, "mapper_parsing_exception", "failed to parse"),
+            ])))
+            .mount(&server)
+            .await;
+
+        let operations = vec![BulkOperation::Create { id: None, doc: json!({ "n": 0 }) }];
+        let err = mock_client(&server).bulk_checked("products", operations).await.unwrap_err();
+        assert!(matches!(&err, ElSearchError::InvalidResponse(reason) if reason.contains("position 1")), "{:?}", err);
+    }
+}
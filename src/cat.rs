@@ -0,0 +1,308 @@
+use elasticsearch::{ cat::{ CatIndicesParts, CatTemplatesParts, CatThreadPoolParts, CatTransformsParts }, params::Bytes };
+use serde::{ de, Deserialize, Deserializer };
+use serde_json::Value;
+use std::fmt::Display;
+use std::str::FromStr;
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+
+#[derive(Debug, Deserialize)]
+pub struct TransformSummary {
+    pub id: String,
+    pub state: String,
+    #[serde(deserialize_with = "cat_number")]
+    pub checkpoint: u64,
+    #[serde(deserialize_with = "cat_number")]
+    pub documents_processed: u64,
+    #[serde(deserialize_with = "cat_string")]
+    pub last_search_time: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ThreadPoolEntry {
+    pub node_name: String,
+    pub name: String,
+    #[serde(deserialize_with = "cat_number")]
+    pub active: u32,
+    #[serde(deserialize_with = "cat_number")]
+    pub queue: u32,
+    #[serde(deserialize_with = "cat_number")]
+    pub rejected: u64,
+    #[serde(deserialize_with = "cat_number")]
+    pub completed: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TemplateSummary {
+    pub name: String,
+    pub index_patterns: String,
+    #[serde(deserialize_with = "cat_number")]
+    pub order: i32,
+    #[serde(default, deserialize_with = "cat_optional_number")]
+    pub version: Option<u32>,
+    #[serde(default, deserialize_with = "cat_list")]
+    pub composed_of: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IndexSummary {
+    pub index: String,
+    pub health: String,
+    pub status: String,
+    #[serde(rename = "docs.count", default, deserialize_with = "cat_optional_number")]
+    pub docs_count: Option<u64>,
+    #[serde(rename = "store.size", default, deserialize_with = "cat_optional_number")]
+    pub store_size_in_bytes: Option<u64>,
+}
+
+// One plugin on one node; `name` is the plugin, `node` the node it is installed on.
+#[derive(Debug, Clone)]
+pub struct PluginInfo {
+    pub name: String,
+    pub version: String,
+    pub node: String,
+}
+
+// `_cat/plugins` reports the node name in `name` and the plugin name in `component`.
+#[derive(Deserialize)]
+struct CatPluginRow {
+    name: String,
+    component: String,
+    #[serde(deserialize_with = "cat_string")]
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct CatNodeRow {
+    name: String,
+}
+
+// The cat APIs report every column as a string, and as null when a value is not available yet.
+pub(crate) fn cat_number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Default,
+    T::Err: Display,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::String(value)) => value.parse().map_err(de::Error::custom),
+        Some(Value::Number(value)) => value.to_string().parse().map_err(de::Error::custom),
+        _ => Ok(T::default()),
+    }
+}
+
+pub(crate) fn cat_optional_number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::String(value)) if !value.is_empty() => value.parse().map(Some).map_err(de::Error::custom),
+        Some(Value::Number(value)) => value.to_string().parse().map(Some).map_err(de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+// List columns such as `composed_of` come back formatted as "[a, b]".
+pub(crate) fn cat_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+    Ok(value
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+pub(crate) fn cat_string<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+impl ElSearch {
+    #[instrument(skip_all)]
+    pub async fn list_transforms(&self) -> Result<Vec<TransformSummary>, ElSearchError> {
+        let response = Call::cluster("list_transforms")
+            .send_checked(None, self.client
+                .cat()
+                .transforms(CatTransformsParts::None)
+                .format("json")
+                .h(&["id", "state", "checkpoint", "documents_processed", "last_search_time"])
+                .send())
+            .await?;
+        Ok(response.json::<Vec<TransformSummary>>().await?)
+    }
+
+    #[instrument(skip_all)]
+    pub async fn thread_pool_stats(&self) -> Result<Vec<ThreadPoolEntry>, ElSearchError> {
+        let response = Call::cluster("thread_pool_stats")
+            .send_checked(None, self.client
+                .cat()
+                .thread_pool(CatThreadPoolParts::None)
+                .format("json")
+                .h(&["node_name", "name", "active", "queue", "rejected", "completed"])
+                .send())
+            .await?;
+        Ok(response.json::<Vec<ThreadPoolEntry>>().await?)
+    }
+
+    #[instrument(skip_all)]
+    pub async fn installed_plugins(&self) -> Result<Vec<PluginInfo>, ElSearchError> {
+        let response = Call::cluster("installed_plugins")
+            .send_checked(None, self.client
+                .cat()
+                .plugins()
+                .format("json")
+                .h(&["name", "component", "version"])
+                .send())
+            .await?;
+        let rows = response.json::<Vec<CatPluginRow>>().await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| PluginInfo {
+                name: row.component,
+                version: row.version,
+                node: row.name,
+            })
+            .collect())
+    }
+
+    #[instrument(skip_all)]
+    pub async fn ensure_plugin_installed(&self, name: &str) -> Result<(), ElSearchError> {
+        let plugins = self.installed_plugins().await?;
+
+        let response = Call::cluster("ensure_plugin_installed")
+            .send_checked(None, self.client
+                .cat()
+                .nodes()
+                .format("json")
+                .h(&["name"])
+                .send())
+            .await?;
+        let nodes = response.json::<Vec<CatNodeRow>>().await?;
+
+        let missing: Vec<String> = nodes
+            .into_iter()
+            .map(|node| node.name)
+            .filter(|node| !plugins.iter().any(|plugin| &plugin.node == node && plugin.name == name))
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(ElSearchError::PluginNotFound {
+                plugin: name.to_string(),
+                nodes: missing,
+            })
+        }
+    }
+
+    #[instrument(skip_all)]
+    pub async fn list_index_templates(&self, pattern: Option<&str>) -> Result<Vec<TemplateSummary>, ElSearchError> {
+        let parts = match pattern {
+            Some(pattern) => CatTemplatesParts::Name(pattern),
+            None => CatTemplatesParts::None,
+        };
+
+        let response = Call::cluster("list_index_templates")
+            .send_checked(None, self.client
+                .cat()
+                .templates(parts)
+                .format("json")
+                .h(&["name", "index_patterns", "order", "version", "composed_of"])
+                .send())
+            .await?;
+        Ok(response.json::<Vec<TemplateSummary>>().await?)
+    }
+
+    // Closed indices report no document count or store size.
+    #[instrument(skip_all)]
+    pub async fn list_indices(&self, pattern: &str) -> Result<Vec<IndexSummary>, ElSearchError> {
+        let response = Call::new("list_indices", pattern)
+            .send_checked(None, self.client
+                .cat()
+                .indices(CatIndicesParts::Index(&[pattern]))
+                .format("json")
+                .bytes(Bytes::B)
+                .h(&["index", "health", "status", "docs.count", "store.size"])
+                .send())
+            .await?;
+        Ok(response.json::<Vec<IndexSummary>>().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::mock_client;
+
+    #[test]
+    fn numbers_are_read_from_strings_numbers_and_nulls() {
+        let entry: ThreadPoolEntry = serde_json::from_value(json!({
+            "node_name": "node-1", "name": "search", "active": "2", "queue": 5, "rejected": null, "completed": "1234"
+        })).unwrap();
+        assert_eq!((entry.active, entry.queue, entry.rejected, entry.completed), (2, 5, 0, 1234));
+
+        let err = serde_json::from_value::<ThreadPoolEntry>(json!({
+            "node_name": "node-1", "name": "search", "active": "two", "queue": "0", "rejected": "0", "completed": "0"
+        })).unwrap_err();
+        assert!(err.to_string().contains("invalid digit"), "{}", err);
+    }
+
+    #[test]
+    fn optional_numbers_lists_and_strings_tolerate_missing_values() {
+        let template: TemplateSummary = serde_json::from_value(json!({
+            "name": "logs", "index_patterns": "[logs-*]", "order": "0", "version": "", "composed_of": "[base, logs-mappings]"
+        })).unwrap();
+        assert_eq!(template.version, None);
+        assert_eq!(template.composed_of, vec!["base", "logs-mappings"]);
+
+        let template: TemplateSummary = serde_json::from_value(json!({
+            "name": "legacy", "index_patterns": "[old-*]", "order": "1", "version": "7", "composed_of": "[]"
+        })).unwrap();
+        assert_eq!(template.version, Some(7));
+        assert!(template.composed_of.is_empty());
+
+        let closed: IndexSummary = serde_json::from_value(json!({
+            "index": "archive", "health": "", "status": "close", "docs.count": null, "store.size": null
+        })).unwrap();
+        assert_eq!((closed.docs_count, closed.store_size_in_bytes), (None, None));
+
+        let transform: TransformSummary = serde_json::from_value(json!({
+            "id": "t1", "state": "started", "checkpoint": "3", "documents_processed": "10", "last_search_time": null
+        })).unwrap();
+        assert_eq!(transform.last_search_time, "");
+    }
+
+    #[tokio::test]
+    async fn installed_plugins_separate_the_plugin_from_its_node() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/_cat/plugins"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                { "name": "node-1", "component": "analysis-icu", "version": "8.13.0" },
+                { "name": "node-2", "component": "analysis-icu", "version": null }
+            ])))
+            .mount(&server)
+            .await;
+
+        let plugins = mock_client(&server).installed_plugins().await.unwrap();
+        let rows = plugins.iter().map(|plugin| (plugin.name.as_str(), plugin.node.as_str(), plugin.version.as_str())).collect::<Vec<_>>();
+        assert_eq!(rows, vec![("analysis-icu", "node-1", "8.13.0"), ("analysis-icu", "node-2", "")]);
+    }
+}
@@ -0,0 +1,669 @@
+use elasticsearch::{ auth::Credentials, cat::CatFielddataParts, cert::{ Certificate, CertificateValidation }, http::{ request::JsonBody, response::Response, transport::{ CloudConnectionPool, SingleNodeConnectionPool, Transport, TransportBuilder }, StatusCode, Url }, indices::{ IndicesCreateParts, IndicesExistsParts, IndicesValidateQueryParts }, CountParts, Elasticsearch, FieldCapsParts, IndexParts, SearchParts };
+use futures::stream::{ self, Stream };
+use serde_json::{ json, Value };
+use std::collections::VecDeque;
+use std::fs;
+use std::pin::Pin;
+use std::sync::{ atomic::{ AtomicUsize, Ordering }, Arc };
+use std::task::{ Context, Poll };
+use std::time::Duration;
+use tracing::instrument;
+
+use crate::config::{ BasicAuthConfig, CloudConfig, Config };
+use crate::error::ElSearchError;
+use crate::logging::Call;
+use crate::models::{ CompositeBucket, FieldDataEntry };
+use crate::plan::{ lint_query, PlanReport };
+use crate::queries::to_search_body;
+use crate::retry::RetryPolicy;
+use crate::search::IndexSelection;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct ElSearch {
+    pub(crate) client: Elasticsearch,
+    pub(crate) retry_policy: RetryPolicy,
+    pub(crate) log_request_bodies: bool,
+}
+
+impl ElSearch {
+    // Wraps an already configured transport, e.g. one pointed at a mock server in tests.
+    pub fn from_transport(transport: Transport) -> Self {
+        ElSearch {
+            client: Elasticsearch::new(transport),
+            retry_policy: RetryPolicy::default(),
+            log_request_bodies: false,
+        }
+    }
+
+    pub fn new_from_localhost(host: &str) -> Result<Self, ElSearchError> {
+        Self::new_from_localhost_with_timeout(host, DEFAULT_TIMEOUT)
+    }
+
+    pub fn new_from_localhost_with_timeout(host: &str, timeout: Duration) -> Result<Self, ElSearchError> {
+        let url = Url::parse(host)
+            .map_err(|err| ElSearchError::InvalidConfig(format!("invalid Elasticsearch URL {}: {}", host, err)))?;
+        let transport = TransportBuilder::new(SingleNodeConnectionPool::new(url))
+            .timeout(timeout)
+            .build()
+            .map_err(elasticsearch::Error::from)?;
+        Ok(Self::from_transport(transport))
+    }
+
+    pub fn new_from_cloudhost(config: &CloudConfig) -> Result<Self, ElSearchError> {
+        Self::new_from_cloudhost_with_timeout(config, DEFAULT_TIMEOUT)
+    }
+
+    pub fn new_from_cloudhost_with_timeout(config: &CloudConfig, timeout: Duration) -> Result<Self, ElSearchError> {
+        let api_key = &config.api_key;
+        let api_key_id = &config.api_key_id;
+        let cloud_id = &config.cloud_id;
+
+        let credentials = Credentials::ApiKey(api_key_id.to_string(), api_key.to_string());
+        let transport = TransportBuilder::new(CloudConnectionPool::new(cloud_id)?)
+            .auth(credentials)
+            .timeout(timeout)
+            .build()
+            .map_err(elasticsearch::Error::from)?;
+        Ok(Self::from_transport(transport))
+    }
+
+    pub fn new_with_basic_auth(host: &str, username: &str, password: &str) -> Result<Self, ElSearchError> {
+        Self::new_with_basic_auth_with_timeout(host, username, password, DEFAULT_TIMEOUT)
+    }
+
+    pub fn new_with_basic_auth_with_timeout(host: &str, username: &str, password: &str, timeout: Duration) -> Result<Self, ElSearchError> {
+        Self::new_from_basic_auth_config(&BasicAuthConfig {
+            url: host.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            ca_cert: None,
+        }, timeout)
+    }
+
+    fn new_from_basic_auth_config(config: &BasicAuthConfig, timeout: Duration) -> Result<Self, ElSearchError> {
+        let url = Url::parse(&config.url)
+            .map_err(|err| ElSearchError::InvalidConfig(format!("invalid Elasticsearch URL {}: {}", config.url, err)))?;
+        let credentials = Credentials::Basic(config.username.clone(), config.password.clone());
+
+        let mut builder = TransportBuilder::new(SingleNodeConnectionPool::new(url))
+            .auth(credentials)
+            .timeout(timeout);
+        if let Some(ca_cert) = &config.ca_cert {
+            let pem = fs::read(ca_cert)
+                .map_err(|err| ElSearchError::InvalidConfig(format!("cannot read CA certificate {}: {}", ca_cert.display(), err)))?;
+            builder = builder.cert_validation(CertificateValidation::Full(Certificate::from_pem(&pem)?));
+        }
+
+        let transport = builder.build().map_err(elasticsearch::Error::from)?;
+        Ok(Self::from_transport(transport))
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self, ElSearchError> {
+        Self::from_config_with_timeout(config, DEFAULT_TIMEOUT)
+    }
+
+    pub fn from_config_with_timeout(config: &Config, timeout: Duration) -> Result<Self, ElSearchError> {
+        match config {
+            Config::Cloud(cloud) => Self::new_from_cloudhost_with_timeout(cloud, timeout),
+            Config::BasicAuth(basic) => Self::new_from_basic_auth_config(basic, timeout),
+            Config::Local { url } => Self::new_from_localhost_with_timeout(url, timeout),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, ElSearchError> {
+        Self::from_config(&Config::from_env()?)
+    }
+
+    // Query bodies are always logged at debug. Documents can hold sensitive data and bulk
+    // payloads get large, so request bodies carrying documents are only logged when
+    // explicitly enabled. Credentials are never logged.
+    pub fn with_request_body_logging(mut self, enabled: bool) -> Self {
+        self.log_request_bodies = enabled;
+        self
+    }
+
+    pub(crate) fn loggable_document<'v>(&self, body: &'v Value) -> Option<&'v Value> {
+        self.log_request_bodies.then_some(body)
+    }
+
+    // Patterns and multi-index selections ignore missing indices, so a pattern that
+    // matches nothing yields an empty result instead of an error.
+    #[instrument(skip_all, fields(index = tracing::field::Empty))]
+    pub async fn search(&self, index_name: impl Into<IndexSelection>, body: &Value) -> Result<Response, ElSearchError> {
+        let selection = index_name.into();
+        if selection.is_empty() {
+            return Err(ElSearchError::InvalidArgument("search needs at least one index name or pattern".to_string()));
+        }
+        let index = selection.to_string();
+        tracing::Span::current().record("index", tracing::field::display(&index));
+
+        let indices = selection.names();
+        let multi_index = selection.is_multi_index();
+        self.send_with_retry(Call::new("search", &index), Some(body), || self.client
+            .search(SearchParts::Index(&indices))
+            .ignore_unavailable(multi_index)
+            .allow_no_indices(true)
+            .body(body)
+            .send())
+            .await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn add_document(&self, index_name: &str, body: &Value) -> Result<Response, ElSearchError> {
+        let call = Call::new("add_document", index_name);
+        self.send_non_idempotent_with_retry(call, self.loggable_document(body), || self.client
+            .index(IndexParts::Index(index_name))
+            .body(body)
+            .send())
+            .await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn add_document_returning_id(&self, index_name: &str, body: &Value) -> Result<String, ElSearchError> {
+        let resp_body = self.add_document(index_name, body).await?.json::<Value>().await?;
+
+        match resp_body["result"].as_str() {
+            Some("created") | Some("updated") => {}
+            other => return Err(ElSearchError::InvalidResponse(format!("index operation returned result {:?}", other))),
+        }
+
+        resp_body["_id"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| ElSearchError::InvalidResponse("index response has no _id".to_string()))
+    }
+
+    // `index_name` may be a pattern such as "products-*", which exists if it matches any index.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn check_index_exists(&self, index_name: &str) -> Result<bool, ElSearchError> {
+        let call = Call::new("check_index_exists", index_name);
+        let response = call
+            .send(None, self.client
+                .indices()
+                .exists(IndicesExistsParts::Index(&[index_name]))
+                .allow_no_indices(false)
+                .send())
+            .await?;
+        match response.status_code() {
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => call.ensure_success(response).await.map(|_| true),
+        }
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn create_index(&self, index_name: &str, body: &Value) -> Result<Response, ElSearchError> {
+        Call::new("create_index", index_name)
+            .send_checked(Some(body), self.client
+                .indices()
+                .create(IndicesCreateParts::Index(index_name))
+                .body(body)
+                .send())
+            .await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn bulk_create_by_index(&self, index_name: &str, operations: Vec<Value>) -> Result<Response, ElSearchError> {
+        tracing::debug!(op = "bulk_create_by_index", index = index_name, operations = operations.len(), "sending bulk create request");
+        let logged_body = self.log_request_bodies.then(|| Value::from(operations.clone()));
+        let call = Call::new("bulk_create_by_index", index_name);
+        self.send_non_idempotent_with_retry(call, logged_body.as_ref(), || {
+            let mut bulk_body = Vec::<JsonBody::<Value>>::new();
+
+            for operation in &operations {
+                let jsonbody = JsonBody::new(operation.clone());
+                let create_instruction = json!({
+                    "create": {}
+                });
+                let create_instr_jsonbody = JsonBody::new(create_instruction);
+                bulk_body.push(create_instr_jsonbody);
+                bulk_body.push(jsonbody);
+            }
+
+            self.client
+                .bulk(elasticsearch::BulkParts::Index(index_name))
+                .body(bulk_body)
+                .send()
+        })
+        .await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn bulk_create_returning_ids(&self, index_name: &str, operations: Vec<Value>) -> Result<Vec<String>, ElSearchError> {
+        let resp_body = self.bulk_create_by_index(index_name, operations).await?.json::<Value>().await?;
+
+        let items = resp_body["items"]
+            .as_array()
+            .ok_or_else(|| ElSearchError::InvalidResponse("bulk response has no items array".to_string()))?;
+
+        let mut ids = Vec::with_capacity(items.len());
+        for (position, item) in items.iter().enumerate() {
+            let create = &item["create"];
+            let status = create["status"].as_u64().unwrap_or_default() as u16;
+            if !(200..300).contains(&status) {
+                return Err(ElSearchError::BulkItem {
+                    position,
+                    status,
+                    reason: create["error"]["reason"].as_str().unwrap_or("unknown error").to_string(),
+                });
+            }
+            let id = create["_id"]
+                .as_str()
+                .ok_or_else(|| ElSearchError::InvalidResponse(format!("bulk item {} has no _id", position)))?;
+            ids.push(id.to_string());
+        }
+        Ok(ids)
+    }
+
+    async fn composite_page(&self, index_name: &str, sources: &Value, page_size: usize, after_key: Option<&Value>) -> Result<(Vec<CompositeBucket>, Option<Value>), ElSearchError> {
+        let mut composite = json!({
+            "size": page_size,
+            "sources": sources
+        });
+        if let Some(after_key) = after_key {
+            composite["after"] = after_key.clone();
+        }
+
+        let body = json!({
+            "size": 0,
+            "aggs": {
+                "composite_page": {
+                    "composite": composite
+                }
+            }
+        });
+
+        let resp_body = self.search(index_name, &body).await?.json::<Value>().await?;
+        let agg = &resp_body["aggregations"]["composite_page"];
+        let buckets: Vec<CompositeBucket> = serde_json::from_value(agg["buckets"].clone())?;
+        let next_after_key = agg.get("after_key").cloned();
+
+        Ok((buckets, next_after_key))
+    }
+
+    pub fn stream_composite<'a>(&'a self, index_name: &'a str, sources: &'a Value, page_size: usize) -> CompositeStream<'a> {
+        let page_size = page_size.max(1);
+        let pages = Arc::new(AtomicUsize::new(0));
+        let page_counter = Arc::clone(&pages);
+
+        let state = CompositeState {
+            buffer: VecDeque::with_capacity(page_size),
+            after_key: None,
+            exhausted: false,
+        };
+
+        let inner = stream::try_unfold(state, move |mut state| {
+            let page_counter = Arc::clone(&page_counter);
+            async move {
+                loop {
+                    if let Some(bucket) = state.buffer.pop_front() {
+                        return Ok(Some((bucket, state)));
+                    }
+                    if state.exhausted {
+                        return Ok(None);
+                    }
+
+                    let (buckets, after_key) = match self.composite_page(index_name, sources, page_size, state.after_key.as_ref()).await {
+                        Ok(page) => page,
+                        Err(err) => return Err(err),
+                    };
+                    page_counter.fetch_add(1, Ordering::Relaxed);
+
+                    state.exhausted = buckets.len() < page_size || after_key.is_none();
+                    state.after_key = after_key;
+                    state.buffer.extend(buckets);
+                }
+            }
+        });
+
+        CompositeStream {
+            inner: Box::pin(inner),
+            pages,
+        }
+    }
+
+    #[instrument(skip_all)]
+    pub async fn cat_fielddata(&self, fields: &[&str]) -> Result<Vec<FieldDataEntry>, ElSearchError> {
+        let parts = if fields.is_empty() {
+            CatFielddataParts::None
+        } else {
+            CatFielddataParts::Fields(fields)
+        };
+
+        let response = Call::cluster("cat_fielddata")
+            .send_checked(None, self.client
+                .cat()
+                .fielddata(parts)
+                .format("json")
+                .send())
+            .await?;
+        Ok(response.json::<Vec<FieldDataEntry>>().await?)
+    }
+
+    // `body` is a search body or a bare query clause, as for `search_all`. Only a body
+    // without a "query" key is validated and counted as match_all, like Elasticsearch would run it.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn plan_query(&self, index_name: &str, body: &Value) -> Result<PlanReport, ElSearchError> {
+        let body = to_search_body(body)?;
+        let query_body = json!({ "query": body.get("query").cloned().unwrap_or_else(|| json!({ "match_all": {} })) });
+        let call = Call::new("plan_query", index_name);
+
+        let response = call
+            .send(Some(&query_body), self.client
+                .indices()
+                .validate_query(IndicesValidateQueryParts::Index(&[index_name]))
+                .explain(true)
+                .body(&query_body)
+                .send())
+            .await?;
+        // A query Elasticsearch cannot parse is part of the plan, not a failure to plan.
+        let validate_resp = match response.status_code() {
+            StatusCode::NOT_FOUND => return Err(ElSearchError::IndexNotFound(index_name.to_string())),
+            StatusCode::BAD_REQUEST => response.json::<Value>().await?,
+            _ => call.ensure_success(response).await?.json::<Value>().await?,
+        };
+
+        // Counting with an invalid query would only fail again.
+        let count_resp = if validate_resp["valid"].as_bool() == Some(true) {
+            let response = call
+                .send_checked(Some(&query_body), self.client
+                    .count(CountParts::Index(&[index_name]))
+                    .body(&query_body)
+                    .send())
+                .await?;
+            response.json::<Value>().await?
+        } else {
+            Value::Null
+        };
+
+        let response = call
+            .send_checked(None, self.client
+                .field_caps(FieldCapsParts::Index(&[index_name]))
+                .fields(&["*"])
+                .send())
+            .await?;
+        let field_caps = response.json::<Value>().await?;
+
+        Ok(PlanReport::assemble(index_name, &validate_resp, &count_resp, lint_query(&body, &field_caps)))
+    }
+}
+
+struct CompositeState {
+    buffer: VecDeque<CompositeBucket>,
+    after_key: Option<Value>,
+    exhausted: bool,
+}
+
+pub struct CompositeStream<'a> {
+    inner: Pin<Box<dyn Stream<Item = Result<CompositeBucket, ElSearchError>> + 'a>>,
+    pages: Arc<AtomicUsize>,
+}
+
+impl CompositeStream<'_> {
+    pub fn pages_fetched(&self) -> usize {
+        self.pages.load(Ordering::Relaxed)
+    }
+}
+
+impl Stream for CompositeStream<'_> {
+    type Item = Result<CompositeBucket, ElSearchError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use futures::StreamExt;
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, Request, Respond, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::{ mock_client, ndjson_lines, received };
+
+    #[tokio::test]
+    async fn bulk_body_alternates_action_and_source_lines() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_bulk"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "errors": false, "items": [] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let documents = vec![json!({ "name": "Laptop" }), json!({ "name": "Mouse" })];
+        mock_client(&server).bulk_create_by_index("products", documents.clone()).await.unwrap();
+
+        let requests = received(&server).await;
+        let lines = ndjson_lines(&requests[0]);
+        assert_eq!(lines, vec![json!({ "create": {} }), documents[0].clone(), json!({ "create": {} }), documents[1].clone()]);
+    }
+
+    #[tokio::test]
+    async fn too_many_requests_is_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(ResponseTemplate::new(429))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "hits": { "hits": [] } })))
+            .mount(&server)
+            .await;
+
+        let response = mock_client(&server).search("products", &json!({})).await.unwrap();
+        assert!(response.status_code().is_success());
+        assert_eq!(received(&server).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn from_transport_uses_the_given_transport() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let transport = Transport::single_node(&server.uri()).unwrap();
+        assert!(ElSearch::from_transport(transport).ping().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn from_config_with_timeout_applies_the_timeout() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .mount(&server)
+            .await;
+
+        let config = Config::Local { url: server.uri() };
+        let es = ElSearch::from_config_with_timeout(&config, Duration::from_millis(50)).unwrap();
+        let err = es.ping().await.unwrap_err();
+        assert!(matches!(&err, ElSearchError::Transport(err) if err.is_timeout()), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn plan_query_reports_invalid_queries_without_counting() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_validate/query"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "valid": false, "explanations": [{ "index": "products", "valid": false, "error": "unknown query [matchh]" }]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/products/_field_caps"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "fields": {} })))
+            .mount(&server)
+            .await;
+
+        let report = mock_client(&server).plan_query("products", &json!({ "query": { "matchh": {} } })).await.unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.count, None);
+        assert!(received(&server).await.iter().all(|request| !request.url.path().ends_with("/_count")));
+    }
+
+    #[tokio::test]
+    async fn plan_query_fails_when_a_component_request_fails() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_validate/query"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "valid": true })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/products/_count"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "count": 3 })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/products/_field_caps"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server).plan_query("products", &json!({})).await.unwrap_err();
+        assert!(matches!(err, ElSearchError::Api { status: 500, .. }), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn plan_query_plans_a_bare_clause_as_the_query() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_validate/query"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "valid": true })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/products/_count"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "count": 3 })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/products/_field_caps"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "fields": { "name": { "text": { "type": "text", "searchable": true, "aggregatable": false } } }
+            })))
+            .mount(&server)
+            .await;
+
+        let es = mock_client(&server);
+        let clause = json!({ "wildcard": { "name": "*top" } });
+        let report = es.plan_query("products", &clause).await.unwrap();
+        assert!(report.valid);
+        assert_eq!(report.count, Some(3));
+        assert!(!report.hints.is_empty(), "the clause itself is linted");
+
+        let requests = received(&server).await;
+        let bodies = requests
+            .iter()
+            .filter(|request| request.method.as_str() == "POST")
+            .map(|request| serde_json::from_slice::<Value>(&request.body).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(bodies, vec![json!({ "query": clause }), json!({ "query": clause })]);
+
+        let err = es.plan_query("products", &json!({ "term": {}, "match": {} })).await.unwrap_err();
+        assert!(matches!(err, ElSearchError::InvalidQuery(_)), "{:?}", err);
+        assert_eq!(received(&server).await.len(), requests.len(), "an uninterpretable query is not sent");
+    }
+
+    // Serves 7 buckets keyed 0..7 in pages of the requested size, continuing after the
+    // `after` key of the request, so a page size of 2 gives 4 pages with a short last one.
+    struct CompositePages;
+
+    impl Respond for CompositePages {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            const BUCKETS: i64 = 7;
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let composite = &body["aggs"]["composite_page"]["composite"];
+            let size = composite["size"].as_i64().unwrap();
+            let start = composite["after"]["n"].as_i64().map_or(0, |n| n + 1);
+            let end = (start + size).min(BUCKETS);
+
+            let buckets = (start..end).map(|n| json!({ "key": { "n": n }, "doc_count": 1 })).collect::<Vec<_>>();
+            ResponseTemplate::new(200).set_body_json(json!({
+                "hits": { "hits": [] },
+                "aggregations": { "composite_page": { "after_key": { "n": end - 1 }, "buckets": buckets } }
+            }))
+        }
+    }
+
+    async fn composite_server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(CompositePages)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn composite_stream_fetches_every_page_when_fully_consumed() {
+        let server = composite_server().await;
+        let es = mock_client(&server);
+        let sources = json!([{ "n": { "terms": { "field": "n" } } }]);
+
+        let mut buckets = es.stream_composite("products", &sources, 2);
+        let mut keys = Vec::new();
+        while let Some(bucket) = buckets.next().await {
+            keys.push(bucket.unwrap().key["n"].as_i64().unwrap());
+        }
+
+        assert_eq!(keys, (0..7).collect::<Vec<_>>());
+        assert_eq!(buckets.pages_fetched(), 4);
+        let requests = received(&server).await;
+        assert_eq!(requests.len(), 4);
+        let after = |request: &Request| serde_json::from_slice::<Value>(&request.body).unwrap()["aggs"]["composite_page"]["composite"]["after"].clone();
+        assert_eq!(requests.iter().map(after).collect::<Vec<_>>(), vec![Value::Null, json!({ "n": 1 }), json!({ "n": 3 }), json!({ "n": 5 })]);
+    }
+
+    #[tokio::test]
+    async fn composite_stream_only_fetches_the_pages_it_needs() {
+        let server = composite_server().await;
+        let es = mock_client(&server);
+        let sources = json!([{ "n": { "terms": { "field": "n" } } }]);
+
+        let mut buckets = es.stream_composite("products", &sources, 2);
+        for _ in 0..3 {
+            buckets.next().await.unwrap().unwrap();
+        }
+
+        assert_eq!(buckets.pages_fetched(), 2);
+        assert_eq!(received(&server).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn cat_fielddata_rejects_error_responses() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/_cat/fielddata"))
+            .respond_with(ResponseTemplate::new(403).set_body_json(json!({ "error": { "type": "security_exception" }, "status": 403 })))
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server).cat_fielddata(&[]).await.unwrap_err();
+        assert!(matches!(err, ElSearchError::Api { status: 403, .. }), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn empty_index_selection_is_rejected_before_sending() {
+        let server = MockServer::start().await;
+        let es = mock_client(&server);
+
+        let no_indices: &[&str] = &[];
+        for selection in [IndexSelection::from(no_indices), IndexSelection::from("")] {
+            let err = es.search(selection, &json!({})).await.unwrap_err();
+            assert!(matches!(err, ElSearchError::InvalidArgument(_)), "{:?}", err);
+        }
+        assert!(received(&server).await.is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+use std::env;
+use std::path::PathBuf;
+
+use crate::error::ElSearchError;
+
+pub struct CloudConfig {
+    pub api_key: String,
+    pub api_key_id: String,
+    pub cloud_id: String,
+}
+
+pub struct BasicAuthConfig {
+    pub url: String,
+    pub username: String,
+    pub password: String,
+    pub ca_cert: Option<PathBuf>,
+}
+
+pub enum Config {
+    Cloud(CloudConfig),
+    BasicAuth(BasicAuthConfig),
+    Local { url: String },
+}
+
+impl Config {
+    // ES_URL (or the older ES_HOST) selects a single node, authenticated when ES_USERNAME is set;
+    // otherwise the cloud variables are required.
+    pub fn from_env() -> Result<Self, ElSearchError> {
+        if let Some(url) = optional_env("ES_URL").or_else(|| optional_env("ES_HOST")) {
+            return match optional_env("ES_USERNAME") {
+                Some(username) => Ok(Config::BasicAuth(BasicAuthConfig {
+                    url,
+                    username,
+                    password: required_env("ES_PASSWORD")?,
+                    ca_cert: optional_env("ES_CA_CERT").map(PathBuf::from),
+                })),
+                None => Ok(Config::Local { url }),
+            };
+        }
+
+        Ok(Config::Cloud(CloudConfig {
+            api_key: required_env("API_KEY")?,
+            api_key_id: required_env("API_KEY_ID")?,
+            cloud_id: required_env("CLOUD_ID")?,
+        }))
+    }
+}
+
+fn optional_env(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+pub(crate) fn required_env(name: &str) -> Result<String, ElSearchError> {
+    optional_env(name).ok_or_else(|| ElSearchError::MissingConfig(format!("environment variable {} is not set", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{ Mutex, MutexGuard };
+
+    use super::*;
+    use crate::client::ElSearch;
+
+    const VARIABLES: [&str; 8] = ["ES_URL", "ES_HOST", "ES_USERNAME", "ES_PASSWORD", "ES_CA_CERT", "CLOUD_ID", "API_KEY_ID", "API_KEY"];
+
+    // The environment is shared by every test thread, so tests that change it take turns.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    // Sets exactly the given variables for the duration of a test and restores the previous
+    // values when dropped.
+    struct TempEnv {
+        saved: Vec<(&'static str, Option<String>)>,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl TempEnv {
+        fn set(values: &[(&str, &str)]) -> Self {
+            let lock = ENV_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let saved = VARIABLES.iter().map(|name| (*name, env::var(name).ok())).collect();
+            for name in VARIABLES {
+                match values.iter().find(|(key, _)| *key == name) {
+                    Some((_, value)) => env::set_var(name, value),
+                    None => env::remove_var(name),
+                }
+            }
+            TempEnv { saved, _lock: lock }
+        }
+    }
+
+    impl Drop for TempEnv {
+        fn drop(&mut self) {
+            for (name, value) in &self.saved {
+                match value {
+                    Some(value) => env::set_var(name, value),
+                    None => env::remove_var(name),
+                }
+            }
+        }
+    }
+
+    fn missing_variable(result: Result<Config, ElSearchError>) -> String {
+        match result {
+            Err(ElSearchError::MissingConfig(message)) => message,
+            Err(other) => panic!("expected MissingConfig, got {:?}", other),
+            Ok(_) => panic!("expected MissingConfig, got a config"),
+        }
+    }
+
+    #[test]
+    fn es_url_alone_is_an_unauthenticated_node() {
+        let _env = TempEnv::set(&[("ES_URL", "http://localhost:9200")]);
+        assert!(matches!(Config::from_env(), Ok(Config::Local { url }) if url == "http://localhost:9200"));
+    }
+
+    #[test]
+    fn es_host_is_still_accepted() {
+        let _env = TempEnv::set(&[("ES_HOST", "http://old:9200")]);
+        assert!(matches!(Config::from_env(), Ok(Config::Local { url }) if url == "http://old:9200"));
+    }
+
+    #[test]
+    fn username_selects_basic_auth() {
+        let _env = TempEnv::set(&[
+            ("ES_URL", "https://node:9200"),
+            ("ES_USERNAME", "elastic"),
+            ("ES_PASSWORD", "changeme"),
+            ("ES_CA_CERT", "/certs/ca.crt"),
+        ]);
+        let Ok(Config::BasicAuth(config)) = Config::from_env() else { panic!("expected basic auth") };
+        assert_eq!((config.url.as_str(), config.username.as_str(), config.password.as_str()), ("https://node:9200", "elastic", "changeme"));
+        assert_eq!(config.ca_cert, Some(PathBuf::from("/certs/ca.crt")));
+    }
+
+    #[test]
+    fn basic_auth_without_a_password_names_the_variable() {
+        let _env = TempEnv::set(&[("ES_URL", "https://node:9200"), ("ES_USERNAME", "elastic")]);
+        assert!(missing_variable(Config::from_env()).contains("ES_PASSWORD"));
+    }
+
+    #[test]
+    fn cloud_variables_are_required_without_es_url() {
+        let _env = TempEnv::set(&[("CLOUD_ID", "deployment:abc"), ("API_KEY_ID", "id"), ("API_KEY", "key")]);
+        let Ok(Config::Cloud(config)) = Config::from_env() else { panic!("expected cloud") };
+        assert_eq!((config.cloud_id.as_str(), config.api_key_id.as_str(), config.api_key.as_str()), ("deployment:abc", "id", "key"));
+    }
+
+    #[test]
+    fn a_missing_cloud_variable_is_named() {
+        let _env = TempEnv::set(&[("CLOUD_ID", "deployment:abc"), ("API_KEY", "key")]);
+        assert!(missing_variable(Config::from_env()).contains("API_KEY_ID"));
+    }
+
+    #[test]
+    fn empty_values_count_as_unset() {
+        let _env = TempEnv::set(&[("ES_URL", ""), ("CLOUD_ID", "deployment:abc"), ("API_KEY_ID", "id"), ("API_KEY", "key")]);
+        assert!(matches!(Config::from_env(), Ok(Config::Cloud(_))));
+    }
+
+    #[test]
+    fn a_bad_url_is_an_error_instead_of_a_panic() {
+        let result = ElSearch::new_from_localhost("not a url");
+        assert!(matches!(result, Err(ElSearchError::InvalidConfig(_))));
+    }
+}
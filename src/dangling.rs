@@ -0,0 +1,211 @@
+use elasticsearch::{ dangling_indices::DanglingIndicesImportDanglingIndexParts, http::response::Response, indices::IndicesGetSettingsParts };
+use serde_json::{ json, Value };
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+
+// Index settings that Elasticsearch assigns itself and rejects when creating an index.
+const GENERATED_SETTINGS: &[&str] = &["uuid", "creation_date", "provided_name", "version", "history_uuid", "resize"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DanglingConflictStrategy {
+    // Moves the live index with the same name to the given name before importing.
+    // The live index is only deleted once every document has been copied.
+    Rename(String),
+    SkipIfExists,
+    OverwriteExisting,
+}
+
+impl ElSearch {
+    async fn creatable_index_settings(&self, index_name: &str) -> Result<Value, ElSearchError> {
+        let response = Call::cluster("creatable_index_settings")
+            .send_checked(None, self.client
+                .indices()
+                .get_settings(IndicesGetSettingsParts::Index(&[index_name]))
+                .send())
+            .await?;
+        let mut resp_body = response.json::<Value>().await?;
+
+        let mut settings = resp_body[index_name]["settings"]["index"].take();
+        let Some(fields) = settings.as_object_mut() else {
+            return Err(ElSearchError::InvalidResponse(format!("settings response has no entry for index {}", index_name)));
+        };
+        for generated in GENERATED_SETTINGS {
+            fields.remove(*generated);
+        }
+        Ok(settings)
+    }
+
+    // Copies `index_name` into a new index with the same settings and mappings. Fails,
+    // leaving `index_name` untouched, unless every document arrived in `new_name`.
+    async fn move_index(&self, index_name: &str, new_name: &str) -> Result<(), ElSearchError> {
+        let failed = |reason: String| ElSearchError::ReindexFailed {
+            source_index: index_name.to_string(),
+            dest_index: new_name.to_string(),
+            reason,
+        };
+
+        let body = json!({
+            "settings": { "index": self.creatable_index_settings(index_name).await? },
+            "mappings": self.get_mapping(index_name).await?
+        });
+        self.create_index(new_name, &body).await?;
+
+        let outcome = self.reindex(index_name, new_name, None).await?;
+        if let Some(first) = outcome.failures.first() {
+            return Err(failed(format!("{} documents could not be copied, the first because: {}", outcome.failures.len(), first.reason)));
+        }
+
+        self.refresh_index(new_name).await?;
+        let source_count = self.count(index_name, None).await?;
+        let dest_count = self.count(new_name, None).await?;
+        if source_count != dest_count {
+            return Err(failed(format!("{} holds {} documents but {} holds {}", index_name, source_count, new_name, dest_count)));
+        }
+
+        self.delete_index(index_name).await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    pub async fn import_dangling_index(&self, index_uuid: &str, accept_data_loss: bool) -> Result<Response, ElSearchError> {
+        Call::cluster("import_dangling_index")
+            .send_checked(None, self.client
+                .dangling_indices()
+                .import_dangling_index(DanglingIndicesImportDanglingIndexParts::IndexUuid(index_uuid))
+                .accept_data_loss(accept_data_loss)
+                .send())
+            .await
+    }
+
+    #[instrument(skip_all)]
+    pub async fn dangling_index_name(&self, index_uuid: &str) -> Result<String, ElSearchError> {
+        let response = Call::cluster("dangling_index_name")
+            .send_checked(None, self.client
+                .dangling_indices()
+                .list_dangling_indices()
+                .send())
+            .await?;
+        let resp_body = response.json::<Value>().await?;
+
+        resp_body["dangling_indices"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|index| index["index_uuid"].as_str() == Some(index_uuid))
+            .and_then(|index| index["index_name"].as_str())
+            .map(String::from)
+            .ok_or_else(|| ElSearchError::IndexNotFound(index_uuid.to_string()))
+    }
+
+    // Returns `Ok(None)` when the import was skipped because of `SkipIfExists`.
+    #[instrument(skip_all)]
+    pub async fn import_dangling_index_with_strategy(&self, index_uuid: &str, strategy: DanglingConflictStrategy) -> Result<Option<Response>, ElSearchError> {
+        let index_name = self.dangling_index_name(index_uuid).await?;
+
+        if self.check_index_exists(&index_name).await? {
+            match strategy {
+                DanglingConflictStrategy::SkipIfExists => return Ok(None),
+                DanglingConflictStrategy::OverwriteExisting => {
+                    self.delete_index(&index_name).await?;
+                }
+                DanglingConflictStrategy::Rename(new_name) => {
+                    self.move_index(&index_name, &new_name).await?;
+                }
+            }
+        }
+
+        self.import_dangling_index(index_uuid, true).await.map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::{ mock_client, received };
+
+    // A live `products` index clashing with the dangling one, with the given reindex
+    // result and document counts for the old and new index.
+    async fn rename_scenario(server: &MockServer, reindex_response: Value, source_count: u64, dest_count: u64) {
+        let routes = [
+            ("GET", "/_dangling", json!({ "dangling_indices": [{ "index_uuid": "abc", "index_name": "products" }] })),
+            ("HEAD", "/products", json!({})),
+            ("GET", "/products/_settings", json!({
+                "products": { "settings": { "index": {
+                    "number_of_shards": "2", "uuid": "live-uuid", "creation_date": "1700000000000",
+                    "provided_name": "products", "version": { "created": "8050099" }
+                } } }
+            })),
+            ("GET", "/products/_mapping", json!({ "products": { "mappings": { "properties": { "name": { "type": "text" } } } } })),
+            ("PUT", "/products-old", json!({ "acknowledged": true })),
+            ("POST", "/_reindex", json!({ "task": "node:1" })),
+            ("GET", "/_tasks/node:1", json!({ "completed": true, "response": reindex_response })),
+            ("POST", "/products-old/_refresh", json!({})),
+            ("POST", "/products/_count", json!({ "count": source_count })),
+            ("POST", "/products-old/_count", json!({ "count": dest_count })),
+            ("DELETE", "/products", json!({ "acknowledged": true })),
+            ("POST", "/_dangling/abc", json!({ "acknowledged": true })),
+        ];
+        for (http_method, route, body) in routes {
+            Mock::given(method(http_method))
+                .and(path(route))
+                .respond_with(ResponseTemplate::new(200).set_body_json(body))
+                .mount(server)
+                .await;
+        }
+    }
+
+    async fn deleted_or_imported(server: &MockServer) -> Vec<String> {
+        received(server)
+            .await
+            .iter()
+            .filter(|request| request.method.as_str() == "DELETE" || request.url.path().starts_with("/_dangling/"))
+            .map(|request| format!("{} {}", request.method, request.url.path()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn rename_copies_settings_and_mappings_before_deleting() {
+        let server = MockServer::start().await;
+        rename_scenario(&server, json!({ "total": 5, "created": 5, "failures": [] }), 5, 5).await;
+
+        let strategy = DanglingConflictStrategy::Rename("products-old".to_string());
+        let imported = mock_client(&server).import_dangling_index_with_strategy("abc", strategy).await.unwrap();
+        assert!(imported.is_some());
+
+        let requests = received(&server).await;
+        let create = requests.iter().find(|request| request.method.as_str() == "PUT").unwrap();
+        let body: Value = serde_json::from_slice(&create.body).unwrap();
+        assert_eq!(body, json!({
+            "settings": { "index": { "number_of_shards": "2" } },
+            "mappings": { "properties": { "name": { "type": "text" } } }
+        }));
+        assert_eq!(deleted_or_imported(&server).await, vec!["DELETE /products", "POST /_dangling/abc"]);
+    }
+
+    #[tokio::test]
+    async fn rename_keeps_the_original_when_documents_fail_to_copy() {
+        let server = MockServer::start().await;
+        rename_scenario(&server, json!({ "total": 5, "created": 4, "failures": [{ "id": "3" }] }), 5, 4).await;
+
+        let strategy = DanglingConflictStrategy::Rename("products-old".to_string());
+        let err = mock_client(&server).import_dangling_index_with_strategy("abc", strategy).await.unwrap_err();
+        assert!(matches!(err, ElSearchError::ReindexFailed { .. }), "{:?}", err);
+        assert!(deleted_or_imported(&server).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rename_keeps_the_original_when_counts_differ() {
+        let server = MockServer::start().await;
+        rename_scenario(&server, json!({ "total": 5, "created": 5, "failures": [] }), 6, 5).await;
+
+        let strategy = DanglingConflictStrategy::Rename("products-old".to_string());
+        let err = mock_client(&server).import_dangling_index_with_strategy("abc", strategy).await.unwrap_err();
+        assert!(matches!(&err, ElSearchError::ReindexFailed { reason, .. } if reason.contains("6 documents")), "{:?}", err);
+        assert!(deleted_or_imported(&server).await.is_empty());
+    }
+}
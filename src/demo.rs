@@ -0,0 +1,270 @@
+use rust_with_elasticsearch::{ models::get_product_mapping, ElSearch, ElSearchError, Hit, Product };
+use serde_json::{ json, Value };
+
+use crate::generate_product_data;
+
+const DEMO_INDEX: &str = "products-demo";
+
+fn product_id(product: &Value) -> String {
+    product["name"].as_str().unwrap_or_default().to_lowercase().replace(' ', "-")
+}
+
+fn print_products(hits: Vec<Hit<Product>>) {
+    for hit in hits {
+        println!("    {:<18} {:<12} {:>8.2}  ({})", hit.source.name, hit.source.category, hit.source.price, hit.id);
+    }
+}
+
+fn print_buckets(name: &str, buckets: &[(String, i64)]) {
+    println!("    {}:", name);
+    for (key, doc_count) in buckets {
+        println!("      {:<12} {}", key, doc_count);
+    }
+}
+
+// Returns the number of products left in the demo index.
+pub async fn run(es: &ElSearch, cleanup: bool) -> Result<u64, ElSearchError> {
+    println!("[1/8] Ensuring index {} exists", DEMO_INDEX);
+    if es.check_index_exists(DEMO_INDEX).await? {
+        println!("    index already exists");
+    } else {
+        es.create_index(DEMO_INDEX, &get_product_mapping()).await?;
+        println!("    index created");
+    }
+
+    println!("[2/8] Seeding sample products");
+    for product in generate_product_data() {
+        let outcome = es.add_document_with_id(DEMO_INDEX, &product_id(&product), &product).await?;
+        println!("    {:<18} {:?} (version {})", outcome.id, outcome.result, outcome.version);
+    }
+    es.refresh_index(DEMO_INDEX).await?;
+
+    println!("[3/8] Full-text search for \"wireless speaker\"");
+    let text_query = json!({
+        "query": {
+            "multi_match": {
+                "query": "wireless speaker",
+                "fields": ["name", "description"]
+            }
+        }
+    });
+    print_products(es.search_typed::<Product>(DEMO_INDEX, &text_query).await?.hits);
+
+    println!("[4/8] Audio products sorted by price");
+    let filtered_query = json!({
+        "query": {
+            "bool": {
+                "filter": [
+                    { "term": { "category": "Audio" } }
+                ]
+            }
+        },
+        "sort": [
+            { "price": "asc" }
+        ]
+    });
+    print_products(es.search_typed::<Product>(DEMO_INDEX, &filtered_query).await?.hits);
+
+    println!("[5/8] Category and brand facets");
+    print_buckets("category", &es.terms_aggregation(DEMO_INDEX, "category", 10).await?);
+    print_buckets("brand", &es.terms_aggregation(DEMO_INDEX, "brand", 10).await?);
+
+    println!("[6/8] Updating the laptop price");
+    let outcome = es.update_document(DEMO_INDEX, "laptop", &json!({ "price": 1199.99 })).await?;
+    println!("    {} {:?} (version {})", outcome.id, outcome.result, outcome.version);
+
+    println!("[7/8] Deleting the smartwatch");
+    let deleted = es.delete_exists(DEMO_INDEX, "smartwatch").await?;
+    println!("    deleted: {}", deleted);
+    es.refresh_index(DEMO_INDEX).await?;
+
+    println!("[8/8] Summary");
+    let summary = es.search_typed::<Product>(DEMO_INDEX, &json!({ "size": 0 })).await?;
+    let total = summary.total.map(|total| total.value).unwrap_or_default();
+    println!("    {} products in {}", total, DEMO_INDEX);
+
+    if cleanup {
+        es.delete_index(DEMO_INDEX).await?;
+        println!("Removed index {}", DEMO_INDEX);
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::{ Arc, Mutex };
+    use wiremock::{ matchers::path_regex, Mock, MockServer, Request, Respond, ResponseTemplate };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct FakeIndex {
+        exists: bool,
+        properties: Value,
+        documents: BTreeMap<String, Value>,
+    }
+
+    // Just enough of a single-index cluster, kept in memory, for every step of the demo.
+    #[derive(Clone, Default)]
+    struct FakeCluster(Arc<Mutex<FakeIndex>>);
+
+    fn write_result(id: &str, result: &str) -> Value {
+        json!({ "_id": id, "_version": 1, "result": result, "_seq_no": 0, "_primary_term": 1 })
+    }
+
+    impl FakeIndex {
+        // Reports each requested field with the type it was created with.
+        fn field_caps(&self, request: &Request) -> Value {
+            let mut fields = serde_json::Map::new();
+            let requested = request.url.query_pairs().find(|(key, _)| key == "fields").map(|(_, value)| value.into_owned()).unwrap_or_default();
+            for field in requested.split(',') {
+                if let Some(type_) = self.properties[field]["type"].as_str() {
+                    fields.insert(field.to_string(), json!({ type_: { "type": type_, "searchable": true, "aggregatable": type_ != "text" } }));
+                }
+            }
+            json!({ "indices": [DEMO_INDEX], "fields": fields })
+        }
+
+        fn search(&self, body: &Value) -> Result<Value, String> {
+            if let Some(field) = body["aggs"]["terms"]["terms"]["field"].as_str() {
+                let mut counts = BTreeMap::<String, u64>::new();
+                for document in self.documents.values() {
+                    *counts.entry(document[field].as_str().unwrap_or_default().to_string()).or_default() += 1;
+                }
+                let buckets = counts.into_iter().map(|(key, doc_count)| json!({ "key": key, "doc_count": doc_count })).collect::<Vec<_>>();
+                return Ok(json!({ "hits": { "hits": [] }, "aggregations": { "sterms#terms": { "buckets": buckets } } }));
+            }
+
+            let mut matched = Vec::new();
+            for (id, source) in &self.documents {
+                if matches(&body["query"], source)? {
+                    matched.push((id, source));
+                }
+            }
+            if let Some(sort) = body.get("sort") {
+                let Some((field, Value::String(order))) = sort[0].as_object().and_then(|keys| keys.iter().next()) else {
+                    return Err(format!("unsupported sort {}", sort));
+                };
+                matched.sort_by(|(_, a), (_, b)| a[field].as_f64().partial_cmp(&b[field].as_f64()).unwrap());
+                if order == "desc" {
+                    matched.reverse();
+                }
+            }
+
+            let size = body["size"].as_u64().unwrap_or(10) as usize;
+            let hits = matched
+                .iter()
+                .take(size)
+                .map(|(id, source)| json!({ "_id": id, "_source": source }))
+                .collect::<Vec<_>>();
+            Ok(json!({ "took": 1, "hits": { "total": { "value": matched.len(), "relation": "eq" }, "hits": hits } }))
+        }
+    }
+
+    // Evaluates the query shapes the demo sends; anything else is answered with a 400.
+    fn matches(query: &Value, source: &Value) -> Result<bool, String> {
+        if query.is_null() {
+            return Ok(true);
+        }
+        if let Some(multi_match) = query.get("multi_match") {
+            let words = multi_match["query"].as_str().unwrap_or_default().to_lowercase();
+            let fields = multi_match["fields"].as_array().cloned().unwrap_or_default();
+            return Ok(fields.iter().any(|field| {
+                let text = source[field.as_str().unwrap_or_default()].as_str().unwrap_or_default().to_lowercase();
+                words.split_whitespace().any(|word| text.contains(word))
+            }));
+        }
+        if let Some(filters) = query["bool"]["filter"].as_array() {
+            for filter in filters {
+                let Some((field, value)) = filter["term"].as_object().and_then(|term| term.iter().next()) else {
+                    return Err(format!("unsupported filter {}", filter));
+                };
+                if &source[field] != value {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+        Err(format!("unsupported query {}", query))
+    }
+
+    impl Respond for FakeCluster {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let mut index = self.0.lock().unwrap();
+            let rest = request.url.path().trim_start_matches(&format!("/{}", DEMO_INDEX)).to_string();
+            let body = serde_json::from_slice::<Value>(&request.body).unwrap_or_default();
+            let (action, id) = rest.trim_start_matches('/').split_once('/').unwrap_or((rest.trim_start_matches('/'), ""));
+
+            match (request.method.as_str(), action) {
+                ("HEAD", "") => ResponseTemplate::new(if index.exists { 200 } else { 404 }),
+                ("PUT", "") => {
+                    index.exists = true;
+                    index.properties = body["mappings"]["properties"].clone();
+                    ResponseTemplate::new(200).set_body_json(json!({ "acknowledged": true }))
+                }
+                ("DELETE", "") => {
+                    *index = FakeIndex::default();
+                    ResponseTemplate::new(200).set_body_json(json!({ "acknowledged": true }))
+                }
+                ("POST", "_refresh") => ResponseTemplate::new(200).set_body_json(json!({})),
+                ("GET", "_field_caps") => ResponseTemplate::new(200).set_body_json(index.field_caps(request)),
+                ("POST", "_search") => match index.search(&body) {
+                    Ok(resp_body) => ResponseTemplate::new(200).set_body_json(resp_body),
+                    Err(reason) => ResponseTemplate::new(400).set_body_string(reason),
+                },
+                ("PUT", "_doc") => {
+                    let result = if index.documents.insert(id.to_string(), body).is_some() { "updated" } else { "created" };
+                    ResponseTemplate::new(200).set_body_json(write_result(id, result))
+                }
+                ("POST", "_update") => {
+                    let Some(document) = index.documents.get_mut(id) else {
+                        return ResponseTemplate::new(404).set_body_json(json!({ "error": { "type": "document_missing_exception" }, "status": 404 }));
+                    };
+                    for (field, value) in body["doc"].as_object().unwrap() {
+                        document[field] = value.clone();
+                    }
+                    ResponseTemplate::new(200).set_body_json(write_result(id, "updated"))
+                }
+                ("DELETE", "_doc") => match index.documents.remove(id) {
+                    Some(_) => ResponseTemplate::new(200).set_body_json(write_result(id, "deleted")),
+                    None => ResponseTemplate::new(404).set_body_json(write_result(id, "not_found")),
+                },
+                _ => ResponseTemplate::new(400).set_body_string(format!("unexpected {} {}", request.method, request.url.path())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn demo_runs_end_to_end_and_can_be_repeated() {
+        let server = MockServer::start().await;
+        let cluster = FakeCluster::default();
+        Mock::given(path_regex(format!("^/{}", DEMO_INDEX)))
+            .respond_with(cluster.clone())
+            .mount(&server)
+            .await;
+        let es = ElSearch::new_from_localhost(&server.uri()).unwrap();
+        let seeded = generate_product_data().len() as u64;
+
+        // Seeding uses fixed ids, so a second run ends with the same products.
+        assert_eq!(run(&es, false).await.unwrap(), seeded - 1);
+        let searches = server.received_requests().await.unwrap()
+            .iter()
+            .filter(|request| request.url.path().ends_with("/_search"))
+            .map(|request| serde_json::from_slice::<Value>(&request.body).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(searches[0]["query"], json!({ "multi_match": { "query": "wireless speaker", "fields": ["name", "description"] } }));
+        assert_eq!(searches[1]["query"], json!({ "bool": { "filter": [{ "term": { "category": "Audio" } }] } }));
+        assert_eq!(searches[1]["sort"], json!([{ "price": "asc" }]));
+        assert_eq!(run(&es, false).await.unwrap(), seeded - 1);
+        {
+            let index = cluster.0.lock().unwrap();
+            assert_eq!(index.documents["laptop"]["price"], json!(1199.99));
+            assert!(!index.documents.contains_key("smartwatch"));
+        }
+
+        assert_eq!(run(&es, true).await.unwrap(), seeded - 1);
+        assert!(!cluster.0.lock().unwrap().exists);
+    }
+}
@@ -0,0 +1,453 @@
+use elasticsearch::{ http::{ response::Response, StatusCode }, DeleteByQueryParts, DeleteParts, GetParts, IndexParts, MgetParts, UpdateParts };
+use serde::{ de::DeserializeOwned, Deserialize, Serialize };
+use serde_json::{ json, Value };
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::{ read_json, ElSearchError };
+use crate::logging::Call;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteResult {
+    Created,
+    Updated,
+    Deleted,
+    NotFound,
+    Noop,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WriteOutcome {
+    #[serde(rename = "_id")]
+    pub id: String,
+    #[serde(rename = "_version")]
+    pub version: i64,
+    pub result: WriteResult,
+    #[serde(rename = "_seq_no", default)]
+    pub seq_no: Option<i64>,
+    #[serde(rename = "_primary_term", default)]
+    pub primary_term: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeleteByQueryOutcome {
+    pub total: u64,
+    pub deleted: u64,
+    pub version_conflicts: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct VersionedDoc<T> {
+    pub id: String,
+    pub seq_no: i64,
+    pub primary_term: i64,
+    pub source: T,
+}
+
+fn not_found_outcome(id: &str) -> WriteOutcome {
+    WriteOutcome {
+        id: id.to_string(),
+        version: 0,
+        result: WriteResult::NotFound,
+        seq_no: None,
+        primary_term: None,
+    }
+}
+
+// Elasticsearch only reports the current sequence number inside the reason, e.g.
+// "... current document has seqNo [6] and primary term [1]".
+fn current_seq_no(reason: &str) -> Option<i64> {
+    let (_, rest) = reason.split_once("current document has seqNo [")?;
+    rest.split(']').next()?.parse().ok()
+}
+
+async fn parse_write_outcome(call: Call<'_>, response: Response, id: &str) -> Result<WriteOutcome, ElSearchError> {
+    match response.status_code() {
+        StatusCode::CONFLICT => {
+            let resp_body = response.json::<Value>().await?;
+            let reason = resp_body["error"]["reason"].as_str().unwrap_or("version conflict");
+            Err(ElSearchError::VersionConflict {
+                reason: reason.to_string(),
+                current_seq_no: current_seq_no(reason),
+            })
+        }
+        StatusCode::NOT_FOUND => {
+            let resp_body = response.json::<Value>().await?;
+            if resp_body["result"].as_str() == Some("not_found") {
+                return Ok(serde_json::from_value(resp_body)?);
+            }
+            if resp_body["error"]["type"].as_str() == Some("index_not_found_exception") {
+                let index_name = resp_body["error"]["index"].as_str().unwrap_or_default();
+                return Err(ElSearchError::IndexNotFound(index_name.to_string()));
+            }
+            Ok(not_found_outcome(id))
+        }
+        _ => Ok(call.ensure_success(response).await?.json::<WriteOutcome>().await?),
+    }
+}
+
+fn deserialize_source<T: DeserializeOwned>(id: &str, doc: &Value) -> Result<T, ElSearchError> {
+    serde_json::from_value(doc["_source"].clone()).map_err(|source| ElSearchError::InvalidDocument {
+        id: id.to_string(),
+        source,
+    })
+}
+
+impl ElSearch {
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn add_document_with_id(&self, index_name: &str, id: &str, body: &Value) -> Result<WriteOutcome, ElSearchError> {
+        let call = Call::new("add_document_with_id", index_name);
+        let response = call
+            .send(self.loggable_document(body), self.client
+                .index(IndexParts::IndexId(index_name, id))
+                .body(body)
+                .send())
+            .await?;
+        parse_write_outcome(call, response, id).await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn get_document<T: DeserializeOwned>(&self, index_name: &str, id: &str) -> Result<Option<T>, ElSearchError> {
+        let document = self.get_document_versioned::<T>(index_name, id).await?;
+        Ok(document.map(|document| document.source))
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn get_document_versioned<T: DeserializeOwned>(&self, index_name: &str, id: &str) -> Result<Option<VersionedDoc<T>>, ElSearchError> {
+        let call = Call::new("get_document", index_name);
+        let response = call
+            .send(None, self.client
+                .get(GetParts::IndexId(index_name, id))
+                .send())
+            .await?;
+
+        if response.status_code() == StatusCode::NOT_FOUND {
+            let resp_body = read_json::<Value>(response).await?;
+            if resp_body["error"]["type"].as_str() == Some("index_not_found_exception") {
+                return Err(ElSearchError::IndexNotFound(index_name.to_string()));
+            }
+            return Ok(None);
+        }
+
+        let resp_body = read_json::<Value>(call.ensure_success(response).await?).await?;
+        Ok(Some(VersionedDoc {
+            id: id.to_string(),
+            seq_no: resp_body["_seq_no"].as_i64().unwrap_or_default(),
+            primary_term: resp_body["_primary_term"].as_i64().unwrap_or_default(),
+            source: deserialize_source(id, &resp_body)?,
+        }))
+    }
+
+    // Replaces the document only if nobody wrote it since it was read with `seq_no` and
+    // `primary_term`; otherwise fails with VersionConflict.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn update_document_if_unchanged<T: Serialize>(&self, index_name: &str, id: &str, doc: &T, seq_no: i64, primary_term: i64) -> Result<WriteOutcome, ElSearchError> {
+        let call = Call::new("update_document_if_unchanged", index_name);
+        let logged_body = self.log_request_bodies.then(|| serde_json::to_value(doc)).transpose()?;
+        let response = call
+            .send(logged_body.as_ref(), self.client
+                .index(IndexParts::IndexId(index_name, id))
+                .if_seq_no(seq_no)
+                .if_primary_term(primary_term)
+                .body(doc)
+                .send())
+            .await?;
+        parse_write_outcome(call, response, id).await
+    }
+
+    // Read-modify-write loop: re-reads the document and reapplies `update` whenever another
+    // writer got in between, up to `max_attempts` times.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn update_with_retry<T, F>(&self, index_name: &str, id: &str, max_attempts: u32, mut update: F) -> Result<WriteOutcome, ElSearchError>
+    where
+        T: DeserializeOwned + Serialize,
+        F: FnMut(T) -> T,
+    {
+        let mut attempt = 1;
+        loop {
+            let Some(document) = self.get_document_versioned::<T>(index_name, id).await? else {
+                return Ok(not_found_outcome(id));
+            };
+
+            let updated = update(document.source);
+            match self.update_document_if_unchanged(index_name, id, &updated, document.seq_no, document.primary_term).await {
+                Err(ElSearchError::VersionConflict { .. }) if attempt < max_attempts => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+
+    // Returns one entry per requested id, in the same order, with None for documents that do not exist.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn mget<T: DeserializeOwned>(&self, index_name: &str, ids: &[&str]) -> Result<Vec<Option<T>>, ElSearchError> {
+        let call = Call::new("mget", index_name);
+        let body = json!({ "ids": ids });
+        let response = call
+            .send(Some(&body), self.client
+                .mget(MgetParts::Index(index_name))
+                .body(&body)
+                .send())
+            .await?;
+        if response.status_code() == StatusCode::NOT_FOUND {
+            return Err(ElSearchError::IndexNotFound(index_name.to_string()));
+        }
+        let resp_body = call.ensure_success(response).await?.json::<Value>().await?;
+
+        let docs = resp_body["docs"]
+            .as_array()
+            .ok_or_else(|| ElSearchError::InvalidResponse("mget response has no docs array".to_string()))?;
+        if docs.len() != ids.len() {
+            return Err(ElSearchError::InvalidResponse(format!("mget returned {} docs for {} ids", docs.len(), ids.len())));
+        }
+
+        ids.iter()
+            .zip(docs)
+            .map(|(id, doc)| {
+                if let Some(reason) = doc["error"]["reason"].as_str() {
+                    return Err(ElSearchError::InvalidResponse(format!("document {} could not be fetched: {}", id, reason)));
+                }
+                if doc["found"].as_bool() != Some(true) {
+                    return Ok(None);
+                }
+                deserialize_source(id, doc).map(Some)
+            })
+            .collect()
+    }
+
+    // Alias of `upsert_document`: applies `partial` with doc_as_upsert, creating the document if needed.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn update_or_insert(&self, index_name: &str, id: &str, partial: &Value) -> Result<WriteOutcome, ElSearchError> {
+        self.upsert_document(index_name, id, partial).await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn upsert_document(&self, index_name: &str, id: &str, partial: &Value) -> Result<WriteOutcome, ElSearchError> {
+        let call = Call::new("upsert_document", index_name);
+        let body = json!({
+            "doc": partial,
+            "doc_as_upsert": true
+        });
+        let response = call
+            .send(self.loggable_document(&body), self.client
+                .update(UpdateParts::IndexId(index_name, id))
+                .body(&body)
+                .send())
+            .await?;
+        parse_write_outcome(call, response, id).await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn update_document(&self, index_name: &str, id: &str, partial: &Value) -> Result<WriteOutcome, ElSearchError> {
+        let call = Call::new("update_document", index_name);
+        let body = json!({
+            "doc": partial
+        });
+        let response = call
+            .send(self.loggable_document(&body), self.client
+                .update(UpdateParts::IndexId(index_name, id))
+                .body(&body)
+                .send())
+            .await?;
+        parse_write_outcome(call, response, id).await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn delete_document(&self, index_name: &str, id: &str) -> Result<WriteOutcome, ElSearchError> {
+        let call = Call::new("delete_document", index_name);
+        let response = call
+            .send(None, self.client
+                .delete(DeleteParts::IndexId(index_name, id))
+                .send())
+            .await?;
+        parse_write_outcome(call, response, id).await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn delete_exists(&self, index_name: &str, id: &str) -> Result<bool, ElSearchError> {
+        let outcome = self.delete_document(index_name, id).await?;
+        Ok(outcome.result == WriteResult::Deleted)
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn delete_by_query(&self, index_name: &str, query: &Value) -> Result<DeleteByQueryOutcome, ElSearchError> {
+        let response = Call::new("delete_by_query", index_name)
+            .send_checked(Some(query), self.client
+                .delete_by_query(DeleteByQueryParts::Index(&[index_name]))
+                .body(query)
+                .send())
+            .await?;
+        Ok(response.json::<DeleteByQueryOutcome>().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::join_all;
+    use serde_json::json;
+    use std::sync::{ Arc, Mutex };
+    use std::time::Duration;
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, Request, Respond, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::mock_client;
+
+    #[derive(Default)]
+    struct CounterState {
+        seq_no: i64,
+        count: i64,
+        conflicts: u32,
+    }
+
+    // A single document `{"count": n}` that honours if_seq_no the way Elasticsearch does.
+    #[derive(Clone, Default)]
+    struct CounterDocument(Arc<Mutex<CounterState>>);
+
+    impl Respond for CounterDocument {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let mut state = self.0.lock().unwrap();
+            if request.method.as_str() == "GET" {
+                // Holding the read back lets a concurrent reader see the same version.
+                return ResponseTemplate::new(200)
+                    .set_body_json(json!({
+                        "_id": "c", "_seq_no": state.seq_no, "_primary_term": 1, "found": true,
+                        "_source": { "count": state.count }
+                    }))
+                    .set_delay(Duration::from_millis(50));
+            }
+
+            let expected = request.url.query_pairs().find(|(key, _)| key == "if_seq_no").map(|(_, value)| value.parse::<i64>().unwrap());
+            if expected != Some(state.seq_no) {
+                state.conflicts += 1;
+                return ResponseTemplate::new(409).set_body_json(json!({
+                    "error": {
+                        "type": "version_conflict_engine_exception",
+                        "reason": format!("[c]: version conflict, required seqNo [{}], primary term [1]. current document has seqNo [{}] and primary term [1]", expected.unwrap_or_default(), state.seq_no)
+                    },
+                    "status": 409
+                }));
+            }
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            state.count = body["count"].as_i64().unwrap();
+            state.seq_no += 1;
+            ResponseTemplate::new(200).set_body_json(json!({
+                "_id": "c", "_version": state.seq_no + 1, "result": "updated", "_seq_no": state.seq_no, "_primary_term": 1
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn racing_increments_converge() {
+        let server = MockServer::start().await;
+        let document = CounterDocument::default();
+        Mock::given(method("GET"))
+            .and(path("/counters/_doc/c"))
+            .respond_with(document.clone())
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/counters/_doc/c"))
+            .respond_with(document.clone())
+            .mount(&server)
+            .await;
+
+        let es = mock_client(&server);
+        let increment = |mut doc: Value| {
+            doc["count"] = json!(doc["count"].as_i64().unwrap() + 1);
+            doc
+        };
+        // Both reads go out before either write, so one of the writes has to be retried.
+        let results = join_all((0..2).map(|_| es.update_with_retry::<Value, _>("counters", "c", 5, increment))).await;
+        for result in results {
+            result.unwrap();
+        }
+
+        let state = document.0.lock().unwrap();
+        assert_eq!(state.count, 2);
+        assert!(state.conflicts >= 1);
+    }
+
+    #[tokio::test]
+    async fn stale_write_is_a_version_conflict() {
+        let server = MockServer::start().await;
+        let document = CounterDocument::default();
+        Mock::given(method("PUT"))
+            .and(path("/counters/_doc/c"))
+            .respond_with(document.clone())
+            .mount(&server)
+            .await;
+
+        let es = mock_client(&server);
+        es.update_document_if_unchanged("counters", "c", &json!({ "count": 1 }), 0, 1).await.unwrap();
+        let err = es.update_document_if_unchanged("counters", "c", &json!({ "count": 1 }), 0, 1).await.unwrap_err();
+        assert!(matches!(err, ElSearchError::VersionConflict { current_seq_no: Some(1), .. }), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn missing_document_is_none() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/products/_doc/nope"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "_index": "products", "_id": "nope", "found": false
+            })))
+            .mount(&server)
+            .await;
+
+        let document = mock_client(&server).get_document::<Value>("products", "nope").await.unwrap();
+        assert!(document.is_none());
+    }
+
+    #[tokio::test]
+    async fn missing_index_on_get_is_index_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/gone/_doc/1"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "error": { "type": "index_not_found_exception", "reason": "no such index [gone]" },
+                "status": 404
+            })))
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server).get_document::<Value>("gone", "1").await.unwrap_err();
+        assert!(matches!(err, ElSearchError::IndexNotFound(index) if index == "gone"));
+    }
+
+    #[tokio::test]
+    async fn found_document_is_deserialized() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/products/_doc/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "_index": "products", "_id": "1", "_seq_no": 7, "_primary_term": 2, "found": true,
+                "_source": { "name": "Laptop" }
+            })))
+            .mount(&server)
+            .await;
+
+        let document = mock_client(&server).get_document_versioned::<Value>("products", "1").await.unwrap().unwrap();
+        assert_eq!((document.seq_no, document.primary_term), (7, 2));
+        assert_eq!(document.source, json!({ "name": "Laptop" }));
+    }
+
+    #[tokio::test]
+    async fn update_or_insert_sends_doc_as_upsert() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_update/new"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "_id": "new", "_version": 1, "result": "created", "_seq_no": 0, "_primary_term": 1
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let outcome = mock_client(&server).update_or_insert("products", "new", &json!({ "price": 5 })).await.unwrap();
+        assert_eq!(outcome.result, WriteResult::Created);
+
+        let requests = server.received_requests().await.unwrap();
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body, json!({ "doc": { "price": 5 }, "doc_as_upsert": true }));
+    }
+}
@@ -0,0 +1,112 @@
+use elasticsearch::http::response::Response;
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ElSearchError {
+    Transport(elasticsearch::Error),
+    Api { status: u16, body: String },
+    Serde(serde_json::Error),
+    MissingConfig(String),
+    InvalidConfig(String),
+    InvalidResponse(String),
+    BulkItem { position: usize, status: u16, reason: String },
+    VersionConflict { reason: String, current_seq_no: Option<i64> },
+    PluginNotFound { plugin: String, nodes: Vec<String> },
+    InvalidArgument(String),
+    IndexNotFound(String),
+    Io(std::io::Error),
+    MalformedDocument { line: usize, reason: String },
+    RetriesExhausted { retries: u32, last: Box<ElSearchError> },
+    Timeout(String),
+    InvalidDocument { id: String, source: serde_json::Error },
+    UnsupportedVersion { found: String, required: String },
+    MappingConflict { index: String, reason: String },
+    Validation { position: usize, field: String, reason: String },
+    ReindexFailed { source_index: String, dest_index: String, reason: String },
+    InvalidQuery(String),
+}
+
+impl ElSearchError {
+    pub(crate) async fn from_response(response: Response) -> Self {
+        let status = response.status_code().as_u16();
+        match response.text().await {
+            Ok(body) => ElSearchError::Api { status, body },
+            Err(err) => ElSearchError::Transport(err),
+        }
+    }
+}
+
+impl fmt::Display for ElSearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElSearchError::Transport(err) => write!(f, "transport error: {}", err),
+            ElSearchError::Api { status, body } => write!(f, "elasticsearch returned {}: {}", status, body),
+            ElSearchError::Serde(err) => write!(f, "serialization error: {}", err),
+            ElSearchError::MissingConfig(name) => write!(f, "missing configuration: {}", name),
+            ElSearchError::InvalidConfig(reason) => write!(f, "invalid configuration: {}", reason),
+            ElSearchError::InvalidResponse(reason) => write!(f, "unexpected response: {}", reason),
+            ElSearchError::BulkItem { position, status, reason } => write!(f, "bulk item {} failed with {}: {}", position, status, reason),
+            ElSearchError::VersionConflict { reason, .. } => write!(f, "version conflict: {}", reason),
+            ElSearchError::IndexNotFound(index_name) => write!(f, "index {} does not exist", index_name),
+            ElSearchError::InvalidArgument(reason) => write!(f, "invalid argument: {}", reason),
+            ElSearchError::PluginNotFound { plugin, nodes } => write!(f, "plugin {} is not installed on nodes: {}", plugin, nodes.join(", ")),
+            ElSearchError::Io(err) => write!(f, "io error: {}", err),
+            ElSearchError::MalformedDocument { line, reason } => write!(f, "malformed document on line {}: {}", line, reason),
+            ElSearchError::RetriesExhausted { retries, last } => write!(f, "gave up after {} retries: {}", retries, last),
+            ElSearchError::Timeout(reason) => write!(f, "timed out: {}", reason),
+            ElSearchError::InvalidDocument { id, source } => write!(f, "document {} could not be deserialized: {}", id, source),
+            ElSearchError::UnsupportedVersion { found, required } => write!(f, "Elasticsearch {} is not supported, {} or newer is required", found, required),
+            ElSearchError::MappingConflict { index, reason } => write!(f, "mapping change rejected for index {} (reindex instead): {}", index, reason),
+            ElSearchError::Validation { position, field, reason } => write!(f, "document #{} is invalid at field {}: {}", position, field, reason),
+            ElSearchError::ReindexFailed { source_index, dest_index, reason } => write!(f, "reindex from {} into {} failed: {}", source_index, dest_index, reason),
+            ElSearchError::InvalidQuery(reason) => write!(f, "invalid query: {}", reason),
+        }
+    }
+}
+
+impl Error for ElSearchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ElSearchError::Transport(err) => Some(err),
+            ElSearchError::Serde(err) => Some(err),
+            ElSearchError::Io(err) => Some(err),
+            ElSearchError::RetriesExhausted { last, .. } => Some(last.as_ref()),
+            ElSearchError::InvalidDocument { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<elasticsearch::Error> for ElSearchError {
+    fn from(err: elasticsearch::Error) -> Self {
+        ElSearchError::Transport(err)
+    }
+}
+
+impl From<serde_json::Error> for ElSearchError {
+    fn from(err: serde_json::Error) -> Self {
+        ElSearchError::Serde(err)
+    }
+}
+
+impl From<std::io::Error> for ElSearchError {
+    fn from(err: std::io::Error) -> Self {
+        ElSearchError::Io(err)
+    }
+}
+
+// How much of an unparseable response body is kept in the error message.
+const BODY_SNIPPET_CHARS: usize = 200;
+
+// Like `Response::json`, but a body that is not the expected JSON (a proxy error page,
+// a truncated response) is reported together with the start of the body.
+pub(crate) async fn read_json<T: DeserializeOwned>(response: Response) -> Result<T, ElSearchError> {
+    let body = response.text().await?;
+    serde_json::from_str(&body).map_err(|err| {
+        let snippet: String = body.chars().take(BODY_SNIPPET_CHARS).collect();
+        let ellipsis = if body.chars().count() > BODY_SNIPPET_CHARS { "..." } else { "" };
+        ElSearchError::InvalidResponse(format!("{} in body: {}{}", err, snippet, ellipsis))
+    })
+}
@@ -0,0 +1,234 @@
+use futures::{ pin_mut, StreamExt };
+use serde_json::{ json, Value };
+use std::fmt;
+use std::time::{ Duration, Instant };
+use tokio::io::{ AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter };
+use tracing::instrument;
+
+use crate::bulk::{ BulkOperation, ChunkLimits };
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::loader::{ FileImportReport, ImportBatch, RejectedLine };
+
+pub const EXPORT_PAGE_SIZE: usize = 1000;
+
+// How the document id is stored in each exported line.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    // `{"_id": "...", "_source": {...}}`
+    #[default]
+    Wrapped,
+    // The source object with the id added under the given field name.
+    IdField(String),
+}
+
+impl ExportFormat {
+    // A complete line, including the trailing newline.
+    fn encode_line(&self, id: String, mut source: Value) -> Result<Vec<u8>, ElSearchError> {
+        let value = match self {
+            ExportFormat::Wrapped => json!({ "_id": id, "_source": source }),
+            ExportFormat::IdField(field) => {
+                let object = source
+                    .as_object_mut()
+                    .ok_or_else(|| ElSearchError::InvalidResponse(format!("document {} has no object source", id)))?;
+                object.insert(field.clone(), Value::String(id));
+                source
+            }
+        };
+        let mut line = serde_json::to_vec(&value)?;
+        line.push(b'\n');
+        Ok(line)
+    }
+
+    fn parse_line(&self, mut line: Value) -> Result<(String, Value), String> {
+        let (id, source) = match self {
+            ExportFormat::Wrapped => (line["_id"].take(), line["_source"].take()),
+            ExportFormat::IdField(field) => {
+                let id = line.as_object_mut().and_then(|object| object.remove(field)).unwrap_or_default();
+                (id, line)
+            }
+        };
+        match id {
+            Value::String(id) if source.is_object() => Ok((id, source)),
+            Value::String(_) => Err("line has no document source".to_string()),
+            _ => Err("line has no string document id".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportStats {
+    pub documents: u64,
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for ExportStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Exported {} documents ({} bytes) in {:.1?}", self.documents, self.bytes, self.elapsed)
+    }
+}
+
+impl ElSearch {
+    // Pages through the index with a point in time and writes one document per line.
+    // Only whole lines are handed to the writer, so when Elasticsearch fails midway the
+    // output is flushed and ends at the last complete document before the error is returned.
+    // `query` is a query clause as for `reindex` or a search body, see `search_all`.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn export_index<W: AsyncWrite + Unpin>(&self, index_name: &str, query: Option<&Value>, format: &ExportFormat, writer: W) -> Result<ExportStats, ElSearchError> {
+        let started = Instant::now();
+        let query = query.cloned().unwrap_or_else(|| json!({}));
+        let mut writer = BufWriter::new(writer);
+        let mut stats = ExportStats::default();
+
+        let hits = self.search_all::<Value>(index_name, &query, EXPORT_PAGE_SIZE);
+        pin_mut!(hits);
+        while let Some(hit) = hits.next().await {
+            let line = match hit.and_then(|hit| format.encode_line(hit.id, hit.source)) {
+                Ok(line) => line,
+                Err(err) => {
+                    writer.flush().await?;
+                    return Err(err);
+                }
+            };
+            writer.write_all(&line).await?;
+            stats.documents += 1;
+            stats.bytes += line.len() as u64;
+
+            if stats.documents % EXPORT_PAGE_SIZE as u64 == 0 {
+                writer.flush().await?;
+                tracing::debug!(documents = stats.documents, bytes = stats.bytes, "export progress");
+            }
+        }
+        writer.flush().await?;
+
+        stats.elapsed = started.elapsed();
+        Ok(stats)
+    }
+
+    // Reads lines written by `export_index` with the same format and indexes them under
+    // their original ids, overwriting documents that already exist.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn import_ndjson<R: AsyncRead + Unpin>(&self, index_name: &str, format: &ExportFormat, reader: R) -> Result<FileImportReport, ElSearchError> {
+        let limits = ChunkLimits::default();
+        let mut lines = BufReader::new(reader).lines();
+        let mut report = FileImportReport::default();
+        let mut batch = ImportBatch::default();
+        let mut line_number = 0;
+
+        while let Some(line) = lines.next_line().await? {
+            line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let parsed = serde_json::from_str::<Value>(&line)
+                .map_err(|err| err.to_string())
+                .and_then(|value| format.parse_line(value));
+            match parsed {
+                Ok((id, doc)) => batch.push(line_number, BulkOperation::Index { id: Some(id), doc }, line.len()),
+                Err(reason) => report.rejected.push(RejectedLine { line: line_number, reason }),
+            }
+            if batch.is_full(limits) {
+                self.flush_import_batch(index_name, std::mem::take(&mut batch), &mut report).await?;
+            }
+        }
+        self.flush_import_batch(index_name, batch, &mut report).await?;
+        self.refresh_after_import(index_name).await;
+
+        report.rejected.sort_by_key(|rejected| rejected.line);
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::fixtures::{ bulk_response, ItemOutcome };
+    use crate::test_support::{ mock_client, ndjson_lines, received };
+
+    #[test]
+    fn id_field_lines_round_trip() {
+        let format = ExportFormat::IdField("sku".to_string());
+        let line = format.encode_line("a-1".to_string(), json!({ "name": "Laptop" })).unwrap();
+        assert_eq!(line.last(), Some(&b'\n'));
+
+        let parsed = format.parse_line(serde_json::from_slice(&line).unwrap()).unwrap();
+        assert_eq!(parsed, ("a-1".to_string(), json!({ "name": "Laptop" })));
+        assert!(ExportFormat::Wrapped.parse_line(json!({ "_id": 7, "_source": {} })).is_err());
+    }
+
+    #[tokio::test]
+    async fn export_wraps_a_bare_query_clause() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_pit"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "pit-1" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "pit_id": "pit-1",
+                "hits": { "hits": [{ "_id": "a", "_source": { "brand": "acme" }, "sort": [0] }] }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/_pit"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "succeeded": true })))
+            .mount(&server)
+            .await;
+
+        let clause = json!({ "term": { "brand": "acme" } });
+        let mut output = Vec::new();
+        let stats = mock_client(&server)
+            .export_index("products", Some(&clause), &ExportFormat::Wrapped, &mut output)
+            .await
+            .unwrap();
+
+        assert_eq!(stats.documents, 1);
+        assert_eq!(String::from_utf8(output).unwrap(), "{\"_id\":\"a\",\"_source\":{\"brand\":\"acme\"}}\n");
+        let requests = received(&server).await;
+        let search = requests.iter().find(|request| request.url.path() == "/_search").unwrap();
+        let body: Value = serde_json::from_slice(&search.body).unwrap();
+        assert_eq!(body["query"], clause);
+    }
+
+    #[tokio::test]
+    async fn import_resubmits_rejected_items_with_their_ids() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_bulk"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(bulk_response(vec![
+                ItemOutcome::Updated,
+                ItemOutcome::failed(429, "es_rejected_execution_exception", "queue is full"),
+            ])))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/products/_bulk"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(bulk_response(vec![ItemOutcome::Updated])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/products/_refresh"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .mount(&server)
+            .await;
+
+        let input = "{\"_id\":\"a\",\"_source\":{\"n\":1}}\n{\"_id\":\"b\",\"_source\":{\"n\":2}}\nnot json\n";
+        let report = mock_client(&server)
+            .import_ndjson("products", &ExportFormat::Wrapped, input.as_bytes())
+            .await
+            .unwrap();
+
+        assert_eq!(report.indexed, 2);
+        assert_eq!(report.rejected.iter().map(|rejected| rejected.line).collect::<Vec<_>>(), vec![3]);
+        let requests = received(&server).await;
+        assert_eq!(ndjson_lines(&requests[1]), vec![json!({ "index": { "_id": "b" } }), json!({ "n": 2 })]);
+    }
+}
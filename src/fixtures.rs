@@ -0,0 +1,319 @@
+use serde_json::{ json, Value };
+
+pub const FIXTURE_INDEX: &str = "fixture";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotalRelation {
+    Eq,
+    Gte,
+}
+
+impl TotalRelation {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TotalRelation::Eq => "eq",
+            TotalRelation::Gte => "gte",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResponseFixture {
+    sources: Vec<Value>,
+    total: u64,
+    relation: TotalRelation,
+    took: u64,
+    max_score: Option<f64>,
+    failed_shards: u32,
+}
+
+impl SearchResponseFixture {
+    pub fn new(sources: Vec<Value>) -> Self {
+        SearchResponseFixture {
+            total: sources.len() as u64,
+            sources,
+            relation: TotalRelation::Eq,
+            took: 1,
+            max_score: Some(1.0),
+            failed_shards: 0,
+        }
+    }
+
+    pub fn total(mut self, total: u64, relation: TotalRelation) -> Self {
+        self.total = total;
+        self.relation = relation;
+        self
+    }
+
+    pub fn took(mut self, took: u64) -> Self {
+        self.took = took;
+        self
+    }
+
+    pub fn max_score(mut self, max_score: Option<f64>) -> Self {
+        self.max_score = max_score;
+        self
+    }
+
+    pub fn failed_shards(mut self, failed_shards: u32) -> Self {
+        self.failed_shards = failed_shards;
+        self
+    }
+
+    pub fn build(self) -> Value {
+        let hits: Vec<Value> = self.sources
+            .into_iter()
+            .enumerate()
+            .map(|(position, source)| json!({
+                "_index": FIXTURE_INDEX,
+                "_id": (position + 1).to_string(),
+                "_score": self.max_score,
+                "_source": source
+            }))
+            .collect();
+
+        let total_shards = self.failed_shards + 1;
+        let failures: Vec<Value> = (0..self.failed_shards)
+            .map(|shard| json!({
+                "shard": shard + 1,
+                "index": FIXTURE_INDEX,
+                "node": "fixture-node",
+                "reason": {
+                    "type": "query_shard_exception",
+                    "reason": "failed to create query"
+                }
+            }))
+            .collect();
+
+        let mut shards = json!({
+            "total": total_shards,
+            "successful": total_shards - self.failed_shards,
+            "skipped": 0,
+            "failed": self.failed_shards
+        });
+        if !failures.is_empty() {
+            shards["failures"] = Value::Array(failures);
+        }
+
+        json!({
+            "took": self.took,
+            "timed_out": false,
+            "_shards": shards,
+            "hits": {
+                "total": {
+                    "value": self.total,
+                    "relation": self.relation.as_str()
+                },
+                "max_score": self.max_score,
+                "hits": hits
+            }
+        })
+    }
+}
+
+pub fn search_response(sources: Vec<Value>, total: u64, took: u64) -> Value {
+    SearchResponseFixture::new(sources)
+        .total(total, TotalRelation::Eq)
+        .took(took)
+        .build()
+}
+
+#[derive(Debug, Clone)]
+pub enum ItemOutcome {
+    Created,
+    Updated,
+    Deleted,
+    NotFound,
+    Failed { status: u16, error_type: String, reason: String },
+}
+
+impl ItemOutcome {
+    pub fn failed(status: u16, error_type: &str, reason: &str) -> Self {
+        ItemOutcome::Failed {
+            status,
+            error_type: error_type.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    fn to_item(&self, id: &str) -> Value {
+        match self {
+            ItemOutcome::Created => json!({
+                "create": { "_index": FIXTURE_INDEX, "_id": id, "_version": 1, "result": "created", "status": 201 }
+            }),
+            ItemOutcome::Updated => json!({
+                "index": { "_index": FIXTURE_INDEX, "_id": id, "_version": 2, "result": "updated", "status": 200 }
+            }),
+            ItemOutcome::Deleted => json!({
+                "delete": { "_index": FIXTURE_INDEX, "_id": id, "_version": 3, "result": "deleted", "status": 200 }
+            }),
+            ItemOutcome::NotFound => json!({
+                "delete": { "_index": FIXTURE_INDEX, "_id": id, "_version": 1, "result": "not_found", "status": 404 }
+            }),
+            ItemOutcome::Failed { status, error_type, reason } => json!({
+                "create": {
+                    "_index": FIXTURE_INDEX,
+                    "_id": id,
+                    "status": status,
+                    "error": {
+                        "type": error_type,
+                        "reason": reason
+                    }
+                }
+            }),
+        }
+    }
+}
+
+pub fn bulk_response(items: Vec<ItemOutcome>) -> Value {
+    let errors = items.iter().any(|item| matches!(item, ItemOutcome::Failed { .. }));
+    let items: Vec<Value> = items
+        .iter()
+        .enumerate()
+        .map(|(position, item)| item.to_item(&(position + 1).to_string()))
+        .collect();
+
+    json!({
+        "took": 3,
+        "errors": errors,
+        "items": items
+    })
+}
+
+pub fn error_response(status: u16, error_type: &str, reason: &str) -> Value {
+    json!({
+        "error": {
+            "root_cause": [
+                {
+                    "type": error_type,
+                    "reason": reason
+                }
+            ],
+            "type": error_type,
+            "reason": reason
+        },
+        "status": status
+    })
+}
+
+// Rows as `_cat/indices?format=json&bytes=b` returns them: every number is a string and
+// sizes are plain byte counts, matching what `list_indices` requests.
+pub fn cat_indices(entries: &[(&str, u64, u64)]) -> Value {
+    let rows: Vec<Value> = entries
+        .iter()
+        .enumerate()
+        .map(|(position, (index, docs_count, store_size_in_bytes))| json!({
+            "health": "green",
+            "status": "open",
+            "index": index,
+            "uuid": format!("fixture-uuid-{}", position + 1),
+            "pri": "1",
+            "rep": "1",
+            "docs.count": docs_count.to_string(),
+            "docs.deleted": "0",
+            "store.size": store_size_in_bytes.to_string(),
+            "pri.store.size": store_size_in_bytes.to_string()
+        }))
+        .collect();
+
+    Value::Array(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::bulk::BulkReport;
+    use crate::cat::IndexSummary;
+    use crate::error::ElSearchError;
+    use crate::search::SearchResult;
+    use crate::test_support::mock_client;
+
+    #[test]
+    fn search_response_round_trips() {
+        let sources = vec![json!({ "name": "Laptop" }), json!({ "name": "Mouse" })];
+        let result = SearchResult::<Value>::from_response_body(search_response(sources.clone(), 40, 9)).unwrap();
+
+        assert_eq!(result.took, 9);
+        let total = result.total.as_ref().unwrap();
+        assert_eq!((total.value, total.relation.as_str()), (40, "eq"));
+        assert_eq!(result.hits.iter().map(|hit| hit.id.as_str()).collect::<Vec<_>>(), vec!["1", "2"]);
+        assert_eq!(result.sources(), sources);
+    }
+
+    #[test]
+    fn search_response_fixture_options_round_trip() {
+        let body = SearchResponseFixture::new(vec![json!({ "name": "Laptop" })])
+            .total(10_000, TotalRelation::Gte)
+            .max_score(None)
+            .failed_shards(2)
+            .build();
+        assert_eq!(body["_shards"]["failures"].as_array().map(Vec::len), Some(2));
+
+        let result = SearchResult::<Value>::from_response_body(body).unwrap();
+        let total = result.total.as_ref().unwrap();
+        assert_eq!((total.value, total.relation.as_str()), (10_000, "gte"));
+        assert_eq!(result.max_score, None);
+        assert_eq!(result.hits[0].score, None);
+    }
+
+    #[test]
+    fn bulk_response_round_trips() {
+        let body = bulk_response(vec![
+            ItemOutcome::Created,
+            ItemOutcome::Updated,
+            ItemOutcome::Deleted,
+            ItemOutcome::NotFound,
+            ItemOutcome::failed(429, "es_rejected_execution_exception", "queue is full"),
+        ]);
+        assert_eq!(body["errors"], json!(true));
+
+        let report = BulkReport::from_response_body(&body, 100).unwrap();
+        assert_eq!(report.successful, 3);
+        let failed = report.failed.iter().map(|item| (item.position, item.status, item.error_type.as_str())).collect::<Vec<_>>();
+        assert_eq!(failed, vec![(103, 404, "unknown"), (104, 429, "es_rejected_execution_exception")]);
+        assert_eq!(report.failed[1].reason, "queue is full");
+    }
+
+    #[tokio::test]
+    async fn error_response_round_trips() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/gone/_doc/1"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(error_response(404, "index_not_found_exception", "no such index [gone]")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/products/_doc/1"))
+            .respond_with(ResponseTemplate::new(500).set_body_json(error_response(500, "illegal_state_exception", "boom")))
+            .mount(&server)
+            .await;
+
+        let es = mock_client(&server);
+        let err = es.get_document::<Value>("gone", "1").await.unwrap_err();
+        assert!(matches!(err, ElSearchError::IndexNotFound(index) if index == "gone"));
+
+        match es.get_document::<Value>("products", "1").await.unwrap_err() {
+            ElSearchError::Api { status, body } => {
+                assert_eq!(status, 500);
+                let body: Value = serde_json::from_str(&body).unwrap();
+                assert_eq!(body["error"]["root_cause"][0]["reason"], json!("boom"));
+            }
+            other => panic!("expected an api error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cat_indices_round_trips() {
+        let rows = cat_indices(&[("products", 12, 4_096), ("logs", 0, 0)]);
+        let summaries: Vec<IndexSummary> = serde_json::from_value(rows).unwrap();
+
+        let parsed = summaries
+            .iter()
+            .map(|summary| (summary.index.as_str(), summary.docs_count, summary.store_size_in_bytes))
+            .collect::<Vec<_>>();
+        assert_eq!(parsed, vec![("products", Some(12), Some(4_096)), ("logs", Some(0), Some(0))]);
+        assert_eq!(summaries[0].health, "green");
+    }
+}
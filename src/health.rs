@@ -0,0 +1,189 @@
+use elasticsearch::{ cluster::ClusterHealthParts, http::StatusCode, params::WaitForStatus };
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+
+// Extra time given to a health request beyond its server-side timeout, so the client
+// does not give up before Elasticsearch answers.
+const HEALTH_REQUEST_MARGIN: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Red,
+    Yellow,
+    Green,
+}
+
+impl HealthStatus {
+    fn as_param(&self) -> WaitForStatus {
+        match self {
+            HealthStatus::Red => WaitForStatus::Red,
+            HealthStatus::Yellow => WaitForStatus::Yellow,
+            HealthStatus::Green => WaitForStatus::Green,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClusterHealth {
+    pub cluster_name: String,
+    pub status: HealthStatus,
+    pub number_of_nodes: u32,
+    pub active_shards: u32,
+    #[serde(default)]
+    pub timed_out: bool,
+}
+
+// Parses "8.5.0" or "8.5.0-SNAPSHOT" into major, minor and patch; "8.5" is read as 8.5.0.
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    let mut parts = version
+        .split('-')
+        .next()?
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    parts.resize(parts.len().max(3), 0);
+    Some(parts)
+}
+
+impl ElSearch {
+    #[instrument(skip_all)]
+    pub async fn with_version_check(self, min_version: &str) -> Result<Self, ElSearchError> {
+        let required = parse_version(min_version)
+            .ok_or_else(|| ElSearchError::InvalidArgument(format!("{} is not a valid version", min_version)))?;
+
+        let response = Call::cluster("with_version_check")
+            .send_checked(None, self.client.info().send())
+            .await?;
+        let resp_body = response.json::<Value>().await?;
+        let found = resp_body["version"]["number"]
+            .as_str()
+            .ok_or_else(|| ElSearchError::InvalidResponse("info response has no version.number".to_string()))?;
+        let found_parts = parse_version(found)
+            .ok_or_else(|| ElSearchError::InvalidResponse(format!("cannot parse cluster version {}", found)))?;
+
+        if found_parts < required {
+            return Err(ElSearchError::UnsupportedVersion {
+                found: found.to_string(),
+                required: min_version.to_string(),
+            });
+        }
+        Ok(self)
+    }
+
+    #[instrument(skip_all)]
+    pub async fn ping(&self) -> Result<bool, ElSearchError> {
+        let response = Call::cluster("ping").send(None, self.client.ping().send()).await?;
+        Ok(response.status_code().is_success())
+    }
+
+    // Connection errors count as "not ready yet", so this can be called while the
+    // Elasticsearch container is still starting.
+    #[instrument(skip_all)]
+    pub async fn wait_until_ready(&self, max_attempts: u32, delay: Duration) -> Result<(), ElSearchError> {
+        for attempt in 1..=max_attempts {
+            match self.ping().await {
+                Ok(true) => return Ok(()),
+                Ok(false) => tracing::debug!(attempt, "elasticsearch is not ready yet"),
+                Err(err) => tracing::debug!(attempt, error = %err, "elasticsearch is not reachable yet"),
+            }
+            if attempt < max_attempts {
+                tokio::time::sleep(delay).await;
+            }
+        }
+        Err(ElSearchError::Timeout(format!("Elasticsearch was not ready after {} attempts", max_attempts)))
+    }
+
+    #[instrument(skip_all)]
+    pub async fn cluster_health(&self) -> Result<ClusterHealth, ElSearchError> {
+        let response = Call::cluster("cluster_health")
+            .send_checked(None, self.client
+                .cluster()
+                .health(ClusterHealthParts::None)
+                .send())
+            .await?;
+        Ok(response.json::<ClusterHealth>().await?)
+    }
+
+    #[instrument(skip_all)]
+    pub async fn wait_for_status(&self, status: HealthStatus, timeout: Duration) -> Result<ClusterHealth, ElSearchError> {
+        let timeout_param = format!("{}ms", timeout.as_millis());
+        let call = Call::cluster("wait_for_status");
+        let response = call
+            .send(None, self.client
+                .cluster()
+                .health(ClusterHealthParts::None)
+                .wait_for_status(status.as_param())
+                .timeout(&timeout_param)
+                .request_timeout(timeout + HEALTH_REQUEST_MARGIN)
+                .send())
+            .await?;
+
+        // Elasticsearch answers a health request that timed out with 408 and the last known health.
+        if response.status_code() == StatusCode::REQUEST_TIMEOUT {
+            let resp_body = response.json::<Value>().await?;
+            return Err(ElSearchError::Timeout(format!(
+                "cluster did not reach {:?} within {:?} (status is {})",
+                status,
+                timeout,
+                resp_body["status"].as_str().unwrap_or("unknown")
+            )));
+        }
+
+        let health = call.ensure_success(response).await?.json::<ClusterHealth>().await?;
+        if health.timed_out {
+            return Err(ElSearchError::Timeout(format!("cluster did not reach {:?} within {:?} (status is {:?})", status, timeout, health.status)));
+        }
+        Ok(health)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{ matchers::{ method, path, query_param }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::fast_retry_policy;
+
+    #[tokio::test]
+    async fn wait_for_status_outlives_the_client_timeout() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/_cluster/health"))
+            .and(query_param("wait_for_status", "green"))
+            .and(query_param("timeout", "1000ms"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_json(json!({ "cluster_name": "test", "status": "green", "number_of_nodes": 1, "active_shards": 3 }))
+                .set_delay(Duration::from_millis(400)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let es = ElSearch::new_from_localhost_with_timeout(&server.uri(), Duration::from_millis(100))
+            .unwrap()
+            .with_retry_policy(fast_retry_policy());
+        let health = es.wait_for_status(HealthStatus::Green, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(health.status, HealthStatus::Green);
+    }
+
+    #[tokio::test]
+    async fn health_request_timeout_is_reported() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/_cluster/health"))
+            .respond_with(ResponseTemplate::new(408).set_body_json(json!({ "status": "yellow", "timed_out": true })))
+            .mount(&server)
+            .await;
+
+        let es = ElSearch::new_from_localhost(&server.uri()).unwrap();
+        let err = es.wait_for_status(HealthStatus::Green, Duration::from_millis(10)).await.unwrap_err();
+        assert!(matches!(&err, ElSearchError::Timeout(reason) if reason.contains("yellow")), "{:?}", err);
+    }
+}
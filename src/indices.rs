@@ -0,0 +1,291 @@
+use elasticsearch::{ http::{ headers::HeaderMap, request::JsonBody, response::Response, Method, StatusCode }, indices::{ IndicesAddBlockParts, IndicesDeleteParts, IndicesExistsIndexTemplateParts, IndicesPutIndexTemplateParts, IndicesPutSettingsParts, IndicesRefreshParts, IndicesStatsParts } };
+use serde_json::{ json, Value };
+use std::collections::HashMap;
+use std::time::{ Duration, Instant };
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+use crate::models::Indexable;
+
+const INDEX_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexBlock {
+    ReadOnly,
+    Read,
+    Write,
+    Metadata,
+}
+
+impl IndexBlock {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndexBlock::ReadOnly => "read_only",
+            IndexBlock::Read => "read",
+            IndexBlock::Write => "write",
+            IndexBlock::Metadata => "metadata",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FieldDiskUsage {
+    pub total_in_bytes: u64,
+    pub inverted_index_in_bytes: u64,
+    pub stored_fields_in_bytes: u64,
+    pub doc_values_in_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IndexStats {
+    pub docs_count: u64,
+    pub docs_deleted: u64,
+    pub store_size_in_bytes: u64,
+    pub primary_store_size_in_bytes: u64,
+    pub indexing_total: u64,
+    pub search_query_total: u64,
+}
+
+impl IndexStats {
+    fn from_stats_entry(entry: &Value) -> Self {
+        IndexStats {
+            docs_count: entry["total"]["docs"]["count"].as_u64().unwrap_or_default(),
+            docs_deleted: entry["total"]["docs"]["deleted"].as_u64().unwrap_or_default(),
+            store_size_in_bytes: entry["total"]["store"]["size_in_bytes"].as_u64().unwrap_or_default(),
+            primary_store_size_in_bytes: entry["primaries"]["store"]["size_in_bytes"].as_u64().unwrap_or_default(),
+            indexing_total: entry["total"]["indexing"]["index_total"].as_u64().unwrap_or_default(),
+            search_query_total: entry["total"]["search"]["query_total"].as_u64().unwrap_or_default(),
+        }
+    }
+}
+
+impl ElSearch {
+    #[instrument(skip_all)]
+    pub async fn ensure_index<T: Indexable>(&self) -> Result<(), ElSearchError> {
+        let index_name = T::index_name();
+        if !self.check_index_exists(index_name).await? {
+            self.create_index(index_name, &T::mapping()).await?;
+        }
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn delete_index(&self, index_name: &str) -> Result<Response, ElSearchError> {
+        let call = Call::new("delete_index", index_name);
+        let response = call
+            .send(None, self.client
+                .indices()
+                .delete(IndicesDeleteParts::Index(&[index_name]))
+                .send())
+            .await?;
+        if response.status_code() == StatusCode::NOT_FOUND {
+            return Err(ElSearchError::IndexNotFound(index_name.to_string()));
+        }
+        call.ensure_success(response).await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn delete_index_if_exists(&self, index_name: &str) -> Result<bool, ElSearchError> {
+        match self.delete_index(index_name).await {
+            Ok(_) => Ok(true),
+            Err(ElSearchError::IndexNotFound(_)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn recreate_index(&self, index_name: &str, mapping: &Value) -> Result<Response, ElSearchError> {
+        self.delete_index_if_exists(index_name).await?;
+        self.create_index(index_name, mapping).await
+    }
+
+    // `mapping` is a create index body such as `get_product_mapping()`; the alias is
+    // added to its "aliases", next to any aliases it already declares, so the index and
+    // its write alias appear in a single request.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn create_index_with_alias(&self, index_name: &str, alias: &str, mapping: &Value) -> Result<Response, ElSearchError> {
+        let mut body = mapping.clone();
+        let Some(fields) = body.as_object_mut() else {
+            return Err(ElSearchError::InvalidArgument("create index body must be a JSON object".to_string()));
+        };
+        let aliases = fields.entry("aliases").or_insert_with(|| json!({}));
+        let Some(aliases) = aliases.as_object_mut() else {
+            return Err(ElSearchError::InvalidArgument("\"aliases\" in the create index body must be a JSON object".to_string()));
+        };
+        aliases.insert(alias.to_string(), json!({ "is_write_index": true }));
+
+        self.create_index(index_name, &body).await
+    }
+
+    // Polls every 100ms; returns false if the index has not appeared within `timeout`.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn wait_for_index(&self, index_name: &str, timeout: Duration) -> Result<bool, ElSearchError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.check_index_exists(index_name).await? {
+                return Ok(true);
+            }
+            if Instant::now() >= deadline {
+                return Ok(false);
+            }
+            tokio::time::sleep(INDEX_POLL_INTERVAL).await;
+        }
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn refresh_index(&self, index_name: &str) -> Result<Response, ElSearchError> {
+        Call::new("refresh_index", index_name)
+            .send_checked(None, self.client
+                .indices()
+                .refresh(IndicesRefreshParts::Index(&[index_name]))
+                .send())
+            .await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn add_index_block(&self, index_name: &str, block: IndexBlock) -> Result<Response, ElSearchError> {
+        Call::new("add_index_block", index_name)
+            .send_checked(None, self.client
+                .indices()
+                .add_block(IndicesAddBlockParts::IndexBlock(&[index_name], block.as_str()))
+                .send())
+            .await
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn remove_index_block(&self, index_name: &str, block: IndexBlock) -> Result<Response, ElSearchError> {
+        let setting = format!("index.blocks.{}", block.as_str());
+        let body = json!({ setting: false });
+        Call::new("remove_index_block", index_name)
+            .send_checked(Some(&body), self.client
+                .indices()
+                .put_settings(IndicesPutSettingsParts::Index(&[index_name]))
+                .body(&body)
+                .send())
+            .await
+    }
+
+    #[instrument(skip_all)]
+    pub async fn template_exists(&self, template_name: &str) -> Result<bool, ElSearchError> {
+        let call = Call::cluster("template_exists");
+        let response = call
+            .send(None, self.client
+                .indices()
+                .exists_index_template(IndicesExistsIndexTemplateParts::Name(template_name))
+                .send())
+            .await?;
+        if response.status_code() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        call.ensure_success(response).await.map(|_| true)
+    }
+
+    // Registers the composable index template unless one with that name already exists.
+    // Returns whether the template was created.
+    #[instrument(skip_all)]
+    pub async fn ensure_template(&self, template_name: &str, template: &Value) -> Result<bool, ElSearchError> {
+        if self.template_exists(template_name).await? {
+            return Ok(false);
+        }
+
+        Call::cluster("ensure_template")
+            .send_checked(Some(template), self.client
+                .indices()
+                .put_index_template(IndicesPutIndexTemplateParts::Name(template_name))
+                .create(true)
+                .body(template)
+                .send())
+            .await?;
+        Ok(true)
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn index_disk_usage(&self, index_name: &str, run_expensive_tasks: bool) -> Result<HashMap<String, FieldDiskUsage>, ElSearchError> {
+        let path = format!("/{}/_disk_usage", index_name);
+        let query_string = [("run_expensive_tasks", run_expensive_tasks)];
+
+        let response = Call::new("index_disk_usage", index_name)
+            .send_checked(None, self.client
+                .send(Method::Post, &path, HeaderMap::new(), Some(&query_string), Option::<JsonBody<Value>>::None, None))
+            .await?;
+        let resp_body = response.json::<Value>().await?;
+
+        let fields = resp_body[index_name]["fields"]
+            .as_object()
+            .ok_or_else(|| ElSearchError::InvalidResponse(format!("disk usage response has no fields for index {}", index_name)))?;
+
+        Ok(fields
+            .iter()
+            .map(|(field, usage)| {
+                let usage = FieldDiskUsage {
+                    total_in_bytes: usage["total_in_bytes"].as_u64().unwrap_or_default(),
+                    inverted_index_in_bytes: usage["inverted_index"]["total_in_bytes"].as_u64().unwrap_or_default(),
+                    stored_fields_in_bytes: usage["stored_fields_in_bytes"].as_u64().unwrap_or_default(),
+                    doc_values_in_bytes: usage["doc_values_in_bytes"].as_u64().unwrap_or_default(),
+                };
+                (field.clone(), usage)
+            })
+            .collect())
+    }
+
+    #[instrument(skip_all)]
+    pub async fn all_indices_stats(&self) -> Result<HashMap<String, IndexStats>, ElSearchError> {
+        self.indices_stats_matching("_all").await
+    }
+
+    // `filter_by_pattern` accepts anything Elasticsearch accepts as an index
+    // expression, e.g. "products-*" or "logs-2024-*,metrics-*".
+    #[instrument(skip_all)]
+    pub async fn indices_stats_matching(&self, filter_by_pattern: &str) -> Result<HashMap<String, IndexStats>, ElSearchError> {
+        let response = Call::cluster("indices_stats_matching")
+            .send_checked(None, self.client
+                .indices()
+                .stats(IndicesStatsParts::Index(&[filter_by_pattern]))
+                .send())
+            .await?;
+        let resp_body = response.json::<Value>().await?;
+
+        let indices = resp_body["indices"]
+            .as_object()
+            .ok_or_else(|| ElSearchError::InvalidResponse("stats response has no indices object".to_string()))?;
+
+        Ok(indices
+            .iter()
+            .map(|(index_name, entry)| (index_name.clone(), IndexStats::from_stats_entry(entry)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::{ mock_client, received };
+
+    #[tokio::test]
+    async fn create_index_with_alias_keeps_the_aliases_already_declared() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/products-v2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "acknowledged": true, "index": "products-v2" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mapping = json!({
+            "mappings": { "properties": { "name": { "type": "text" } } },
+            "aliases": { "products-read": {} }
+        });
+        mock_client(&server).create_index_with_alias("products-v2", "products", &mapping).await.unwrap();
+
+        let body: Value = serde_json::from_slice(&received(&server).await[0].body).unwrap();
+        assert_eq!(body["mappings"], mapping["mappings"]);
+        assert_eq!(body["aliases"], json!({
+            "products-read": {},
+            "products": { "is_write_index": true }
+        }));
+    }
+}
@@ -0,0 +1,42 @@
+pub mod aggregations;
+pub mod aliases;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod bulk;
+pub mod cat;
+pub mod client;
+pub mod config;
+pub mod dangling;
+pub mod documents;
+pub mod error;
+pub mod export;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+pub mod health;
+pub mod indices;
+pub mod loader;
+mod logging;
+pub mod mapping;
+pub mod models;
+pub mod pit;
+pub mod plan;
+pub mod queries;
+pub mod reindex;
+pub mod retry;
+pub mod scroll;
+pub mod search;
+pub mod shutdown;
+pub mod snapshots;
+pub mod split;
+#[cfg(test)]
+mod test_support;
+pub mod validation;
+
+pub use bulk::{ BulkChunkError, BulkItemError, BulkOp, BulkOperation, BulkReport };
+pub use client::{ CompositeStream, ElSearch };
+pub use config::{ BasicAuthConfig, CloudConfig, Config };
+pub use error::ElSearchError;
+pub use models::{ Indexable, Product };
+pub use queries::{ QueryBuilder, SearchQuery };
+pub use retry::RetryPolicy;
+pub use search::{ Hit, IndexSelection, Page, SearchResult };
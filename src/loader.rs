@@ -0,0 +1,328 @@
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use tokio::io::{ AsyncBufReadExt, BufReader };
+use tracing::instrument;
+
+use crate::bulk::{ BulkOperation, ChunkLimits };
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+
+#[derive(Debug, Clone)]
+pub struct RejectedLine {
+    // 1-based line number for NDJSON files, 1-based element position for JSON array files.
+    pub line: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FileImportReport {
+    pub indexed: usize,
+    pub rejected: Vec<RejectedLine>,
+}
+
+impl fmt::Display for FileImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "File import: {} indexed, {} rejected", self.indexed, self.rejected.len())?;
+        for rejected in &self.rejected {
+            writeln!(f, "  line {}: {}", rejected.line, rejected.reason)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_json_array(contents: &[u8]) -> bool {
+    contents.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'[')
+}
+
+// Splits a top-level JSON array into the raw bytes of its elements without parsing them,
+// so the file can be read a chunk at a time and one malformed element does not stop the
+// elements after it. Only strings and bracket depth are tracked to find the separating commas.
+#[derive(Default)]
+struct ArrayElements {
+    started: bool,
+    finished: bool,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    element: Vec<u8>,
+}
+
+impl ArrayElements {
+    // Returns an element once the comma or bracket that ends it has been fed.
+    fn feed(&mut self, byte: u8) -> Option<Vec<u8>> {
+        if self.finished {
+            return None;
+        }
+        if !self.started {
+            self.started = byte == b'[';
+            return None;
+        }
+
+        if self.in_string {
+            self.element.push(byte);
+            match byte {
+                _ if self.escaped => self.escaped = false,
+                b'\\' => self.escaped = true,
+                b'"' => self.in_string = false,
+                _ => {}
+            }
+            return None;
+        }
+
+        match byte {
+            b',' if self.depth == 0 => return Some(std::mem::take(&mut self.element)),
+            b']' if self.depth == 0 => {
+                self.finished = true;
+                return self.finish();
+            }
+            b'"' => self.in_string = true,
+            b'[' | b'{' => self.depth += 1,
+            b']' | b'}' => self.depth = self.depth.saturating_sub(1),
+            _ if byte.is_ascii_whitespace() && self.element.is_empty() => return None,
+            _ => {}
+        }
+        self.element.push(byte);
+        None
+    }
+
+    // The element still being read at the end of the input, e.g. when the array is truncated.
+    fn finish(&mut self) -> Option<Vec<u8>> {
+        let element = std::mem::take(&mut self.element);
+        (!element.is_empty()).then_some(element)
+    }
+}
+
+fn parse_element(position: usize, element: &[u8]) -> Result<Value, ElSearchError> {
+    serde_json::from_slice::<Value>(element).map_err(|err| ElSearchError::MalformedDocument {
+        line: position,
+        reason: err.to_string(),
+    })
+}
+
+fn parse_documents(contents: &str) -> Result<Vec<Value>, ElSearchError> {
+    if is_json_array(contents.as_bytes()) {
+        let mut elements = ArrayElements::default();
+        let mut documents = Vec::new();
+        for byte in contents.bytes().chain(std::iter::once(b']')) {
+            if let Some(element) = elements.feed(byte) {
+                documents.push(parse_element(documents.len() + 1, &element)?);
+            }
+        }
+        return Ok(documents);
+    }
+
+    let mut documents = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let document = serde_json::from_str::<Value>(line).map_err(|err| ElSearchError::MalformedDocument {
+            line: index + 1,
+            reason: err.to_string(),
+        })?;
+        documents.push(document);
+    }
+    Ok(documents)
+}
+
+pub fn load_documents_from_file(path: &Path) -> Result<Vec<Value>, ElSearchError> {
+    let contents = fs::read_to_string(path)?;
+    parse_documents(&contents)
+}
+
+// Bulk operations read from a file, with the line each one came from.
+#[derive(Default)]
+pub(crate) struct ImportBatch {
+    lines: Vec<usize>,
+    operations: Vec<BulkOperation>,
+    bytes: usize,
+}
+
+impl ImportBatch {
+    pub(crate) fn push(&mut self, line: usize, operation: BulkOperation, bytes: usize) {
+        self.lines.push(line);
+        self.operations.push(operation);
+        self.bytes += bytes;
+    }
+
+    pub(crate) fn is_full(&self, limits: ChunkLimits) -> bool {
+        self.operations.len() >= limits.max_docs || self.bytes >= limits.max_bytes
+    }
+}
+
+impl ElSearch {
+    pub(crate) async fn flush_import_batch(&self, index_name: &str, batch: ImportBatch, report: &mut FileImportReport) -> Result<(), ElSearchError> {
+        if batch.operations.is_empty() {
+            return Ok(());
+        }
+
+        // A batch that cannot be sent at all is rejected line by line so the lines already
+        // indexed stay in the report, like a failed chunk in `bulk_create_chunked_with`.
+        let bulk_report = match self.bulk_checked(index_name, batch.operations).await {
+            Ok(bulk_report) => bulk_report,
+            Err(err) => {
+                let reason = format!("bulk request failed: {}", err);
+                report.rejected.extend(batch.lines.into_iter().map(|line| RejectedLine { line, reason: reason.clone() }));
+                return Ok(());
+            }
+        };
+        report.indexed += bulk_report.successful;
+        for item in bulk_report.failed {
+            let line = batch.lines.get(item.position).copied().ok_or_else(|| {
+                ElSearchError::InvalidResponse(format!("bulk response reports item {} of {}", item.position, batch.lines.len()))
+            })?;
+            report.rejected.push(RejectedLine {
+                line,
+                reason: format!("[{}] {}: {}", item.status, item.error_type, item.reason),
+            });
+        }
+        Ok(())
+    }
+
+    async fn push_import_line(&self, index_name: &str, line: usize, parsed: Result<Value, String>, bytes: usize, batch: &mut ImportBatch, report: &mut FileImportReport) -> Result<(), ElSearchError> {
+        match parsed {
+            Ok(document) => batch.push(line, BulkOperation::Create { id: None, doc: document }, bytes),
+            Err(reason) => report.rejected.push(RejectedLine { line, reason }),
+        }
+        if batch.is_full(ChunkLimits::default()) {
+            self.flush_import_batch(index_name, std::mem::take(batch), report).await?;
+        }
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn bulk_index_from_file(&self, index_name: &str, path: &Path) -> Result<FileImportReport, ElSearchError> {
+        let mut reader = BufReader::new(tokio::fs::File::open(path).await?);
+        let mut report = FileImportReport::default();
+        let mut batch = ImportBatch::default();
+
+        // Both formats are read a buffer at a time: a JSON array element by element, NDJSON
+        // line by line. Malformed documents are rejected and the import carries on.
+        if is_json_array(reader.fill_buf().await?) {
+            let mut elements = ArrayElements::default();
+            let mut position = 0;
+            loop {
+                let chunk = reader.fill_buf().await?;
+                if chunk.is_empty() {
+                    break;
+                }
+                let chunk_len = chunk.len();
+                let completed = chunk.iter().filter_map(|byte| elements.feed(*byte)).collect::<Vec<_>>();
+                reader.consume(chunk_len);
+
+                for element in completed {
+                    position += 1;
+                    let parsed = parse_element(position, &element).map_err(|err| err.to_string());
+                    self.push_import_line(index_name, position, parsed, element.len(), &mut batch, &mut report).await?;
+                }
+            }
+            // Left over only when the array is not closed.
+            if let Some(element) = elements.finish() {
+                position += 1;
+                let parsed = parse_element(position, &element).map_err(|err| err.to_string());
+                self.push_import_line(index_name, position, parsed, element.len(), &mut batch, &mut report).await?;
+            }
+        } else {
+            let mut lines = reader.lines();
+            let mut line_number = 0;
+            while let Some(line) = lines.next_line().await? {
+                line_number += 1;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let parsed = serde_json::from_str::<Value>(&line).map_err(|err| err.to_string());
+                self.push_import_line(index_name, line_number, parsed, line.len(), &mut batch, &mut report).await?;
+            }
+        }
+        self.flush_import_batch(index_name, batch, &mut report).await?;
+
+        report.rejected.sort_by_key(|rejected| rejected.line);
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::{ mock_client, ndjson_lines, received, temp_file, AcknowledgeBulk };
+
+    async fn bulk_server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_bulk"))
+            .respond_with(AcknowledgeBulk)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn indexed_documents(requests: &[wiremock::Request]) -> Vec<Value> {
+        requests.iter().flat_map(ndjson_lines).filter(|line| line.get("create").is_none()).collect()
+    }
+
+    #[test]
+    fn array_elements_are_split_at_top_level_commas_only() {
+        let mut elements = ArrayElements::default();
+        let input = br#" [ {"a": [1, 2], "b": "x,]\"}"} , "s" ,, 3 ] trailing"#;
+        let split = input.iter().filter_map(|byte| elements.feed(*byte)).collect::<Vec<_>>();
+        let split = split.iter().map(|element| String::from_utf8_lossy(element).into_owned()).collect::<Vec<_>>();
+        assert_eq!(split, vec![r#"{"a": [1, 2], "b": "x,]\"}"} "#, r#""s" "#, "", "3 "]);
+    }
+
+    #[test]
+    fn parse_documents_reports_the_malformed_array_element() {
+        let err = parse_documents(r#"[{"n": 1}, {"n": }]"#).unwrap_err();
+        assert!(matches!(err, ElSearchError::MalformedDocument { line: 2, .. }), "{:?}", err);
+        assert_eq!(parse_documents("[]").unwrap(), Vec::<Value>::new());
+    }
+
+    #[tokio::test]
+    async fn array_file_rejects_bad_elements_and_indexes_the_rest() {
+        let server = bulk_server().await;
+        let file = temp_file("loader-array.json", "[\n  {\"n\": 1},\n  {\"n\": },\n  {\"n\": 3}\n]\n");
+
+        let report = mock_client(&server).bulk_index_from_file("products", &file).await.unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(report.indexed, 2);
+        assert_eq!(report.rejected.iter().map(|rejected| rejected.line).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(indexed_documents(&received(&server).await), vec![json!({ "n": 1 }), json!({ "n": 3 })]);
+    }
+
+    #[tokio::test]
+    async fn ndjson_file_rejects_bad_lines_and_indexes_the_rest() {
+        let server = bulk_server().await;
+        let file = temp_file("loader-lines.ndjson", "{\"n\": 1}\nnot json\n\n{\"n\": 4}\n");
+
+        let report = mock_client(&server).bulk_index_from_file("products", &file).await.unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(report.indexed, 2);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].line, 2);
+        assert_eq!(indexed_documents(&received(&server).await), vec![json!({ "n": 1 }), json!({ "n": 4 })]);
+    }
+
+    #[tokio::test]
+    async fn failed_bulk_request_keeps_the_report() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_bulk"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+            .mount(&server)
+            .await;
+        let file = temp_file("loader-failing.ndjson", "{\"n\": 1}\nnot json\n");
+
+        let report = mock_client(&server).bulk_index_from_file("products", &file).await.unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(report.indexed, 0);
+        let rejected = report.rejected.iter().map(|rejected| (rejected.line, rejected.reason.starts_with("bulk request failed"))).collect::<Vec<_>>();
+        assert_eq!(rejected, vec![(1, true), (2, false)]);
+    }
+}
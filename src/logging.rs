@@ -0,0 +1,180 @@
+use elasticsearch::http::response::Response;
+use serde_json::Value;
+use std::future::Future;
+use std::time::Instant;
+
+use crate::error::ElSearchError;
+
+// The operation and index of one Elasticsearch request. Every event about the request
+// carries them as fields instead of relying on the surrounding span, so they also reach
+// consumers of the `log` crate, which never see span fields.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Call<'a> {
+    pub(crate) op: &'static str,
+    pub(crate) index: &'a str,
+}
+
+impl<'a> Call<'a> {
+    pub(crate) fn new(op: &'static str, index: &'a str) -> Self {
+        Call { op, index }
+    }
+
+    // For cluster-level requests that do not target an index.
+    pub(crate) fn cluster(op: &'static str) -> Self {
+        Call { op, index: "" }
+    }
+
+    // `tracing` only formats the body when debug events are enabled.
+    pub(crate) fn log_request(&self, body: Option<&Value>) {
+        match body {
+            Some(body) => tracing::debug!(op = self.op, index = self.index, body = %body, "sending elasticsearch request"),
+            None => tracing::debug!(op = self.op, index = self.index, "sending elasticsearch request"),
+        }
+    }
+
+    pub(crate) fn log_failure(&self, err: &ElSearchError) {
+        tracing::warn!(op = self.op, index = self.index, error = %err, "elasticsearch request failed");
+    }
+
+    pub(crate) fn log_retry(&self, retry: u32, backoff_ms: u64) {
+        tracing::warn!(op = self.op, index = self.index, retry, backoff_ms, "retrying transient elasticsearch failure");
+    }
+
+    // Logs the request, sends it and logs the status, without judging the status.
+    pub(crate) async fn send<Fut>(&self, body: Option<&Value>, send: Fut) -> Result<Response, ElSearchError>
+    where
+        Fut: Future<Output = Result<Response, elasticsearch::Error>>,
+    {
+        self.log_request(body);
+        let started = Instant::now();
+        match send.await {
+            Ok(response) => {
+                let status = response.status_code().as_u16();
+                let elapsed_ms = started.elapsed().as_millis() as u64;
+                tracing::debug!(op = self.op, index = self.index, status, elapsed_ms, "elasticsearch responded");
+                Ok(response)
+            }
+            Err(err) => {
+                let err = ElSearchError::from(err);
+                self.log_failure(&err);
+                Err(err)
+            }
+        }
+    }
+
+    // Turns a non-2xx response into `ElSearchError::Api`, logging the response text at warn.
+    pub(crate) async fn ensure_success(&self, response: Response) -> Result<Response, ElSearchError> {
+        if response.status_code().is_success() {
+            return Ok(response);
+        }
+        let err = ElSearchError::from_response(response).await;
+        self.log_failure(&err);
+        Err(err)
+    }
+
+    pub(crate) async fn send_checked<Fut>(&self, body: Option<&Value>, send: Fut) -> Result<Response, ElSearchError>
+    where
+        Fut: Future<Output = Result<Response, elasticsearch::Error>>,
+    {
+        let response = self.send(body, send).await?;
+        self.ensure_success(response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{ json, Value };
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use crate::client::ElSearch;
+    use crate::config::{ BasicAuthConfig, Config };
+    use crate::fixtures::search_response;
+    use crate::test_support::{ mock_client, CapturedLogs };
+
+    #[tokio::test]
+    async fn spans_carry_the_operation_and_index() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(search_response(vec![json!({ "name": "Laptop" })], 1, 7)))
+            .mount(&server)
+            .await;
+
+        let logs = CapturedLogs::default();
+        let _guard = logs.install();
+        mock_client(&server).search_typed::<Value>("products", &json!({})).await.unwrap();
+
+        let lines = logs.lines();
+        let request = lines.iter().find(|line| line.contains("sending elasticsearch request")).unwrap();
+        assert!(request.contains("search_typed{index=products}:search{index=products}:"), "{}", request);
+        assert!(lines.iter().any(|line| line.contains("search_typed{index=products}") && line.contains("took_ms=7")), "{:?}", lines);
+        assert!(lines.iter().any(|line| line.contains("op=\"search\" index=\"products\" status=200 elapsed_ms=")), "{:?}", lines);
+        assert!(lines.iter().any(|line| line.ends_with("search_typed{index=products}: rust_with_elasticsearch::search: close")), "{:?}", lines);
+    }
+
+    #[tokio::test]
+    async fn document_bodies_are_opt_in_and_credentials_never_logged() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/products/_doc/a"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({ "_id": "a", "_version": 1, "result": "created" })))
+            .mount(&server)
+            .await;
+
+        let config = Config::BasicAuth(BasicAuthConfig {
+            url: server.uri(),
+            username: "elastic".to_string(),
+            password: "hunter2-password".to_string(),
+            ca_cert: None,
+        });
+        let es = ElSearch::from_config(&config).unwrap();
+        let document = json!({ "note": "private-note" });
+
+        let logs = CapturedLogs::default();
+        let _guard = logs.install();
+        es.add_document_with_id("products", "a", &document).await.unwrap();
+        let quiet = logs.lines().join("\n");
+        let es = es.with_request_body_logging(true);
+        es.add_document_with_id("products", "a", &document).await.unwrap();
+        let verbose = logs.lines().join("\n");
+
+        assert!(quiet.contains("op=\"add_document_with_id\"") && !quiet.contains("private-note"), "{}", quiet);
+        assert!(verbose.contains("private-note"), "{}", verbose);
+        for secret in ["hunter2-password", "ZWxhc3RpYzpodW50ZXIyLXBhc3N3b3Jk", "authorization", "Authorization"] {
+            assert!(!verbose.contains(secret), "{} appears in {}", secret, verbose);
+        }
+        // The credentials do reach Elasticsearch, so their absence above means something.
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests.iter().all(|request| request.headers.contains_key("authorization")));
+    }
+
+    // The index and operation are checked on the event itself (quoted, as event fields
+    // are), not on the surrounding span, which `log` consumers never see.
+    #[tokio::test]
+    async fn every_request_logs_its_operation_index_and_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/products"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/products"))
+            .respond_with(ResponseTemplate::new(500).set_body_json(json!({ "error": "boom" })))
+            .mount(&server)
+            .await;
+
+        let logs = CapturedLogs::default();
+        let _guard = logs.install();
+        let es = mock_client(&server);
+        assert!(!es.check_index_exists("products").await.unwrap());
+        assert!(es.delete_index("products").await.is_err());
+
+        let lines = logs.lines();
+        let find = |needle: &str| lines.iter().find(|line| line.contains(needle)).cloned().unwrap_or_default();
+        let exists = find("op=\"check_index_exists\" index=\"products\" status=404");
+        assert!(exists.contains("DEBUG"), "{:?}", lines);
+        let failed = find("op=\"delete_index\" index=\"products\" error=");
+        assert!(failed.contains("WARN") && failed.contains("boom"), "{:?}", lines);
+    }
+}
@@ -1,187 +1,17 @@
-use elasticsearch::{ auth::Credentials, http::{ request::{Body, JsonBody, NdBody}, response::Response, transport::Transport }, indices::{ IndicesCreateParts, IndicesExistsParts }, Elasticsearch, IndexParts, SearchParts };
-use std::error::Error;
-use serde_json::{ json, Value };
-use serde::{ Deserialize, Serialize };
+use clap::{ Parser, Subcommand };
 use dotenv::dotenv;
+use rust_with_elasticsearch::{ bulk::DEFAULT_CHUNK_DOCS, export::ExportFormat, models::get_product_mapping, ElSearch, Hit, Indexable, Product };
+use serde_json::{ json, Value };
 use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::process::ExitCode;
+use tracing_subscriber::{ fmt::format::FmtSpan, EnvFilter };
 
-struct Config {
-    api_key: String,
-    api_key_id: String,
-    cloud_id: String,
-}
-
-struct ElSearch {
-    client: Elasticsearch,
-}
-
-impl ElSearch {
-    fn new_from_localhost(host: &str) -> Self {
-        let transport = Transport::single_node(host).unwrap();
-        let es_client = Elasticsearch::new(transport);
-        ElSearch {
-            client: es_client
-        }
-    }
-
-    fn new_from_cloudhost(config: &Config) -> Self {
-        let api_key = &config.api_key;
-        let api_key_id = &config.api_key_id;
-        let cloud_id = &config.cloud_id;
-
-        let credentials = Credentials::ApiKey(api_key_id.to_string(), api_key.to_string());
-        let transport = Transport::cloud(cloud_id, credentials).unwrap();
-
-        let es_client = Elasticsearch::new(transport);
-
-        ElSearch {
-            client: es_client
-        }
-    }
-
-    async fn search(&self, index_name: &str, body: &Value) -> Result<Response, Box<dyn Error>> {
-        let response = self.client
-            .search(SearchParts::Index(&[index_name]))
-            .body(body)
-            .send()
-            .await?;
-        Ok(response)
-    }
-
-    async fn add_document(&self, index_name: &str, body: &Value) -> Result<Response, Box<dyn Error>> {
-        let response = self.client
-            .index(IndexParts::Index(index_name))
-            .body(body)
-            .send()
-            .await?;
-        Ok(response)
-    }
-
-    async fn check_index_exists(&self, index_name: &str) -> Result<Response, Box<dyn Error>> {
-        let response = self.client
-            .indices()
-            .exists(IndicesExistsParts::Index(&[index_name]))
-            .send()
-            .await?;
-        Ok(response)
-    }
-
-    async fn create_index(&self, index_name: &str, body: &Value) -> Result<Response, Box<dyn Error>> {
-        let response: Response = self.client
-            .indices()
-            .create(IndicesCreateParts::Index(index_name))
-            .body(body)
-            .send()
-            .await?;
-        Ok(response)
-    }
-
-    async fn bulk_create_by_index(&self, index_name: &str, operations: Vec<Value>) -> Result<Response, Box<dyn Error>> {
-        let mut bulk_body = Vec::<JsonBody::<Value>>::new();
-
-        for operation in operations {
-            let jsonbody = JsonBody::new(operation);
-            let create_instruction = json!({
-                "create": {}
-            });
-            let create_instr_jsonbody = JsonBody::new(create_instruction);
-            bulk_body.push(create_instr_jsonbody);
-            bulk_body.push(jsonbody);
-        }
-
-        let response = self.client
-            .bulk(elasticsearch::BulkParts::Index(index_name))
-            .body(bulk_body)
-            .send()
-            .await?;
-
-        Ok(response)
-    }
-    
-}
- 
-fn get_product_mapping() -> Value {
-    json!({
-        "mappings": {
-            "properties": {
-                "name": {
-                    "type": "text",
-                    "analyzer": "standard"
-                },
-                "description": {
-                    "type": "text",
-                    "analyzer": "standard"
-                },
-                "category": {
-                    "type": "keyword"
-                },
-                "brand": {
-                    "type": "keyword"
-                },
-                "price": {
-                    "type": "float"
-                },
-                "rating": {
-                    "type": "float"
-                }
-            }
-        }
-    })
-}
-
-fn create_client() -> Result<Elasticsearch, Box<dyn Error>> {
-    let transport = Transport::single_node("http://localhost:9200")?;
-    Ok(Elasticsearch::new(transport))
-}
-
-async fn search(client: &Elasticsearch, index_name: &str, body: &Value) -> Result<Response, Box<dyn Error>> {
-    let response = client
-        .search(SearchParts::Index(&[index_name]))
-        .body(body)
-        .send()
-        .await?;
-    Ok(response)
-}
-
-async fn create_index(index_name: &str, client: &Elasticsearch, body: &Value) -> Result<Response, Box<dyn Error>> {
-    let response: Response = client
-        .indices()
-        .create(IndicesCreateParts::Index(index_name))
-        .body(body)
-        .send()
-        .await?;
-    Ok(response)
-}
-
-async fn check_index_exists(index_name: &str, client: &Elasticsearch) -> Result<Response, Box<dyn Error>> {
-    let response = client
-        .indices()
-        .exists(IndicesExistsParts::Index(&[index_name]))
-        .send()
-        .await?;
-    Ok(response)
-}
-
-async fn add_document(client: &Elasticsearch, index_name: &str, body: &Value) -> Result<Response, Box<dyn Error>> {
-    let response = client
-        .index(IndexParts::Index(index_name))
-        .body(body)
-        .send()
-        .await?;
-    Ok(response)
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct Product {
-    brand: String,
-    category: String,
-    description: String,
-    name: String,
-    price: f64,
-    rating: f64,
-}
+mod demo;
 
-fn generate_product_data() -> Vec<Value> {
+pub(crate) fn generate_product_data() -> Vec<Value> {
     vec![
         json!({
             "name": "Smartphone",
@@ -242,85 +72,200 @@ fn generate_product_data() -> Vec<Value> {
     ]
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    dotenv().ok();
-
-    let cloud_id = env::var("CLOUD_ID")?;
-    let api_key = env::var("API_KEY")?;
-    let api_key_id = env::var("API_KEY_ID")?;
-
-    let config = Config {
-        api_key,
-        api_key_id,
-        cloud_id
-    };
+#[derive(Parser)]
+#[command(about = "Manage the product catalogue in Elasticsearch")]
+struct Cli {
+    /// Connect to this URL without authentication instead of the environment configuration
+    #[arg(long, global = true)]
+    host: Option<String>,
 
-    let product_index_name = "products";
+    #[command(subcommand)]
+    command: Option<Command>,
+}
 
-    let es = ElSearch::new_from_cloudhost(&config);
-    
-    let is_existing_resp = es.check_index_exists(product_index_name).await?;
-    let index_exists = is_existing_resp.status_code().is_success();
+#[derive(Subcommand)]
+enum Command {
+    /// Create an index, with the product mapping unless a mapping file is given
+    CreateIndex {
+        name: String,
+        #[arg(long)]
+        mapping: Option<PathBuf>,
+    },
+    /// Delete an index
+    DeleteIndex { name: String },
+    /// Index documents from a JSON or NDJSON file, or the sample products when no file is given
+    Seed {
+        name: String,
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Search an index with a query clause and print the matching products
+    Search {
+        name: String,
+        /// Query clause as JSON, e.g. '{"match":{"name":"laptop"}}'; matches everything when omitted
+        #[arg(long)]
+        query: Option<String>,
+        #[arg(long, default_value_t = 10)]
+        size: i64,
+        /// Print one JSON document per line instead of a table
+        #[arg(long)]
+        json: bool,
+        /// Explain the cost and risk of the query instead of running it
+        #[arg(long)]
+        plan: bool,
+    },
+    /// Write every document of an index as NDJSON, with its id, to a file or stdout
+    Export {
+        name: String,
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Index an NDJSON file written by `export`, keeping the original ids
+    Import { name: String, file: PathBuf },
+    /// Fetch a single document by id
+    Get { name: String, id: String },
+    /// Count the documents in an index
+    Count { name: String },
+    /// Run the narrated end-to-end demo
+    Demo {
+        #[arg(long)]
+        cleanup: bool,
+    },
+}
 
-    if index_exists {
-        println!("Index {} exists!", product_index_name);
-    } else {
-        println!("Index {} does not exists! Proceed with creating", product_index_name);
-        let product_mapping = get_product_mapping();
-        let create_resp = es.create_index(product_index_name,  &product_mapping).await?;
+fn read_json_file(path: &Path) -> Result<Value, Box<dyn Error>> {
+    let contents = fs::read_to_string(path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+    let value = serde_json::from_str(&contents).map_err(|err| format!("invalid JSON in {}: {}", path.display(), err))?;
+    Ok(value)
+}
 
-        if create_resp.status_code().is_success() {
-            println!("Index created successfully.");
-        } else {
-            println!("Failed to create index: {:?}", create_resp.text().await?);
-        }
+fn print_product_table(hits: &[Hit<Product>]) {
+    println!("{:<22} {:<20} {:<12} {:<12} {:>9} {:>6}", "ID", "NAME", "BRAND", "CATEGORY", "PRICE", "RATING");
+    for hit in hits {
+        let product = &hit.source;
+        println!(
+            "{:<22} {:<20} {:<12} {:<12} {:>9.2} {:>6.1}",
+            hit.id, product.name, product.brand, product.category, product.price, product.rating
+        );
     }
+}
 
-    // let product_doc = json!({
-    //     "name": "UltraSoft Sonic Toothbrush",
-    //     "description": "A high-frequency sonic toothbrush designed for gentle yet effective cleaning, featuring multiple brushing modes and a long-lasting battery.",
-    //     "category": "Health & Personal Care",
-    //     "brand": "SonicCare",
-    //     "price": 99.99,
-    //     "rating": 4.7
-    // });
-
-    // let add_resp: Response = es.add_document(product_index_name, &product_doc).await?;
-
-    // println!("{:?}", add_resp);
-
-
-    // // Search code
-    // let query = json!(
-    //     {
-    //         "query": {
-    //             "multi_match": {
-    //             "query": "toothbrush",
-    //             "fields": ["name", "description"]
-    //             }
-    //         }
-    //     }
-    // );
-
-    // let search_resp = es.search(product_index_name, &query).await?;
-
-    // let resp_body = search_resp.json::<Value>().await?;
+async fn seed_sample_products(es: &ElSearch) -> Result<(), Box<dyn Error>> {
+    let product_index_name = Product::index_name();
 
-    // for hit in resp_body["hits"]["hits"].as_array().unwrap() {
-    //     let product: Product = serde_json::from_value(hit["_source"].clone())?;
-    //     println!("{:?}", product);
-    // }
+    es.ensure_index::<Product>().await?;
+    println!("Index {} is ready", product_index_name);
 
-    // Bulk operation code
     let products = generate_product_data();
+    let bulk_report = es.bulk_create_chunked(product_index_name, products, DEFAULT_CHUNK_DOCS).await?;
+    print!("{}", bulk_report);
+    Ok(())
+}
 
-    let bulk_resp = es.bulk_create_by_index(product_index_name, products).await?;
+async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let es = match &cli.host {
+        Some(host) => ElSearch::new_from_localhost(host)?,
+        None => ElSearch::from_env()?,
+    };
+    // Only an explicit "1" or "true" opts in, so ES_LOG_REQUEST_BODIES=0 keeps documents out of the logs.
+    let log_bodies = env::var("ES_LOG_REQUEST_BODIES").is_ok_and(|value| matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true"));
+    let es = es.with_request_body_logging(log_bodies);
+    if !es.ping().await? {
+        return Err("Elasticsearch did not answer the ping request".into());
+    }
 
-    let bulk_resp_body  = bulk_resp.json::<Value>().await?;
+    let Some(command) = cli.command else {
+        return seed_sample_products(&es).await;
+    };
 
-    println!("{}", bulk_resp_body);
+    match command {
+        Command::CreateIndex { name, mapping } => {
+            let mapping = match mapping {
+                Some(path) => read_json_file(&path)?,
+                None => get_product_mapping(),
+            };
+            es.create_index(&name, &mapping).await?;
+            println!("Created index {}", name);
+        }
+        Command::DeleteIndex { name } => {
+            es.delete_index(&name).await?;
+            println!("Deleted index {}", name);
+        }
+        Command::Seed { name, file: Some(file) } => {
+            print!("{}", es.bulk_index_from_file(&name, &file).await?);
+        }
+        Command::Seed { name, file: None } => {
+            let bulk_report = es.bulk_create_chunked(&name, generate_product_data(), DEFAULT_CHUNK_DOCS).await?;
+            print!("{}", bulk_report);
+        }
+        Command::Search { name, query, size, json, plan } => {
+            let query: Value = match query {
+                Some(query) => serde_json::from_str(&query).map_err(|err| format!("invalid --query JSON: {}", err))?,
+                None => json!({ "match_all": {} }),
+            };
+            let body = json!({ "query": query, "size": size });
+            if plan {
+                let report = es.plan_query(&name, &body).await?;
+                if json {
+                    println!("{}", report.to_json());
+                } else {
+                    print!("{}", report);
+                }
+                return Ok(());
+            }
 
+            let result = es.search_typed::<Product>(&name, &body).await?;
 
+            if json {
+                for hit in &result.hits {
+                    println!("{}", serde_json::to_string(&hit.source)?);
+                }
+            } else {
+                print_product_table(&result.hits);
+            }
+            for failure in &result.failures {
+                eprintln!("skipped document {}: {}", failure.id, failure.reason);
+            }
+        }
+        Command::Export { name, output: Some(output) } => {
+            let file = tokio::fs::File::create(&output).await?;
+            print!("{}", es.export_index(&name, None, &ExportFormat::Wrapped, file).await?);
+        }
+        Command::Export { name, output: None } => {
+            let stats = es.export_index(&name, None, &ExportFormat::Wrapped, tokio::io::stdout()).await?;
+            eprint!("{}", stats);
+        }
+        Command::Import { name, file } => {
+            let file = tokio::fs::File::open(&file).await?;
+            print!("{}", es.import_ndjson(&name, &ExportFormat::Wrapped, file).await?);
+        }
+        Command::Get { name, id } => match es.get_document::<Value>(&name, &id).await? {
+            Some(document) => println!("{}", serde_json::to_string_pretty(&document)?),
+            None => return Err(format!("document {} not found in index {}", id, name).into()),
+        },
+        Command::Count { name } => {
+            println!("{}", es.count(&name, None).await?);
+        }
+        Command::Demo { cleanup } => {
+            demo::run(&es, cleanup).await?;
+        }
+    }
     Ok(())
 }
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
+
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use elasticsearch::{ http::{ response::Response, StatusCode }, indices::{ IndicesGetMappingParts, IndicesPutMappingParts } };
+use serde_json::{ json, Map, Value };
+use std::fmt;
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+
+#[derive(Debug, Clone, Default)]
+pub struct MappingBuilder {
+    properties: Map<String, Value>,
+}
+
+impl MappingBuilder {
+    pub fn new() -> Self {
+        MappingBuilder::default()
+    }
+
+    fn field(mut self, name: &str, definition: Value) -> Self {
+        self.properties.insert(name.to_string(), definition);
+        self
+    }
+
+    pub fn text(self, name: &str, analyzer: &str) -> Self {
+        self.field(name, json!({ "type": "text", "analyzer": analyzer }))
+    }
+
+    pub fn keyword(self, name: &str) -> Self {
+        self.field(name, json!({ "type": "keyword" }))
+    }
+
+    pub fn float(self, name: &str) -> Self {
+        self.field(name, json!({ "type": "float" }))
+    }
+
+    pub fn double(self, name: &str) -> Self {
+        self.field(name, json!({ "type": "double" }))
+    }
+
+    pub fn integer(self, name: &str) -> Self {
+        self.field(name, json!({ "type": "integer" }))
+    }
+
+    pub fn long(self, name: &str) -> Self {
+        self.field(name, json!({ "type": "long" }))
+    }
+
+    pub fn date(self, name: &str) -> Self {
+        self.field(name, json!({ "type": "date" }))
+    }
+
+    pub fn boolean(self, name: &str) -> Self {
+        self.field(name, json!({ "type": "boolean" }))
+    }
+
+    // The `{"properties": ...}` object accepted by the put mapping API.
+    pub fn build(&self) -> Value {
+        json!({ "properties": self.properties })
+    }
+
+    // A create index body wrapping the mapping.
+    pub fn build_index_body(&self) -> Value {
+        json!({ "mappings": self.build() })
+    }
+}
+
+pub trait EsMapping {
+    fn es_mapping() -> MappingBuilder;
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldMismatch {
+    pub field: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MappingDiff {
+    pub missing: Vec<String>,
+    pub mismatched: Vec<FieldMismatch>,
+}
+
+impl MappingDiff {
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+impl fmt::Display for MappingDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_consistent() {
+            return writeln!(f, "Mapping matches");
+        }
+        for field in &self.missing {
+            writeln!(f, "  missing field {}", field)?;
+        }
+        for mismatch in &self.mismatched {
+            writeln!(f, "  field {} is {} but expected {}", mismatch.field, mismatch.actual, mismatch.expected)?;
+        }
+        Ok(())
+    }
+}
+
+fn field_type(definition: &Value) -> &str {
+    match definition.get("type") {
+        Some(Value::String(type_)) => type_,
+        _ if definition.get("properties").is_some() => "object",
+        _ => "unknown",
+    }
+}
+
+fn diff_properties(prefix: &str, expected: &Value, actual: &Value, diff: &mut MappingDiff) {
+    let Some(expected_fields) = expected["properties"].as_object() else { return };
+
+    for (name, expected_definition) in expected_fields {
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        let actual_definition = &actual["properties"][name];
+
+        if actual_definition.is_null() {
+            diff.missing.push(path);
+            continue;
+        }
+
+        let (expected_type, actual_type) = (field_type(expected_definition), field_type(actual_definition));
+        if expected_type != actual_type {
+            diff.mismatched.push(FieldMismatch {
+                field: path,
+                expected: expected_type.to_string(),
+                actual: actual_type.to_string(),
+            });
+            continue;
+        }
+        diff_properties(&path, expected_definition, actual_definition, diff);
+    }
+}
+
+// illegal_argument_exception is also what a malformed mapping (an unknown parameter,
+// a bad analyzer) gets, so only the messages about changing an existing field count.
+fn is_mapping_conflict(reason: &str) -> bool {
+    if reason.contains("cannot be changed from type") {
+        return true;
+    }
+    reason
+        .split_once("mapper [")
+        .is_some_and(|(_, rest)| rest.split_once(']').is_some_and(|(_, after)| after.trim_start().starts_with("cannot be changed")))
+}
+
+impl ElSearch {
+    // New fields are added in place. Changing the type of an existing field is rejected by
+    // Elasticsearch and surfaces as MappingConflict, meaning the data has to be reindexed.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn put_mapping(&self, index_name: &str, mapping: &Value) -> Result<Response, ElSearchError> {
+        let call = Call::new("put_mapping", index_name);
+        let response = call
+            .send(Some(mapping), self.client
+                .indices()
+                .put_mapping(IndicesPutMappingParts::Index(&[index_name]))
+                .body(mapping)
+                .send())
+            .await?;
+
+        match response.status_code() {
+            StatusCode::NOT_FOUND => Err(ElSearchError::IndexNotFound(index_name.to_string())),
+            StatusCode::BAD_REQUEST => {
+                let resp_body = response.json::<Value>().await?;
+                let error = &resp_body["error"];
+                let reason = error["reason"].as_str().unwrap_or_default();
+                let err = if error["type"].as_str() == Some("illegal_argument_exception") && is_mapping_conflict(reason) {
+                    ElSearchError::MappingConflict {
+                        index: index_name.to_string(),
+                        reason: reason.to_string(),
+                    }
+                } else {
+                    ElSearchError::Api {
+                        status: StatusCode::BAD_REQUEST.as_u16(),
+                        body: resp_body.to_string(),
+                    }
+                };
+                call.log_failure(&err);
+                Err(err)
+            }
+            _ => call.ensure_success(response).await,
+        }
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn get_mapping(&self, index_name: &str) -> Result<Value, ElSearchError> {
+        let response = Call::new("get_mapping", index_name)
+            .send_checked(None, self.client
+                .indices()
+                .get_mapping(IndicesGetMappingParts::Index(&[index_name]))
+                .send())
+            .await?;
+        let mut resp_body = response.json::<Value>().await?;
+
+        // The response is keyed by the concrete index name, which differs from the
+        // requested one when an alias is used.
+        let mapping = resp_body
+            .as_object_mut()
+            .and_then(|indices| indices.values_mut().next())
+            .map(|index| index["mappings"].take());
+        mapping.ok_or_else(|| ElSearchError::InvalidResponse(format!("mapping response has no entry for index {}", index_name)))
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn verify_mapping(&self, index_name: &str, expected: &Value) -> Result<MappingDiff, ElSearchError> {
+        let actual = self.get_mapping(index_name).await?;
+        let mut diff = MappingDiff::default();
+        diff_properties("", expected, &actual, &mut diff);
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::mock_client;
+
+    fn diff(expected: &Value, actual: &Value) -> MappingDiff {
+        let mut diff = MappingDiff::default();
+        diff_properties("", expected, actual, &mut diff);
+        diff
+    }
+
+    #[test]
+    fn matching_mappings_are_consistent() {
+        let mapping = MappingBuilder::new().keyword("brand").float("price").build();
+        let diff = diff(&mapping, &mapping);
+        assert!(diff.is_consistent());
+        assert_eq!(diff.to_string(), "Mapping matches\n");
+    }
+
+    #[test]
+    fn missing_and_mismatched_fields_are_reported_with_their_paths() {
+        let expected = json!({ "properties": {
+            "brand": { "type": "keyword" },
+            "price": { "type": "float" },
+            "variant": { "properties": { "sku": { "type": "keyword" }, "stock": { "type": "integer" } } }
+        } });
+        let actual = json!({ "properties": {
+            "brand": { "type": "text" },
+            "variant": { "properties": { "sku": { "type": "keyword" } } },
+            "extra": { "type": "long" }
+        } });
+
+        let diff = diff(&expected, &actual);
+        assert!(!diff.is_consistent());
+        assert_eq!(diff.missing, vec!["price", "variant.stock"]);
+        let mismatched = diff.mismatched.iter().map(|m| (m.field.as_str(), m.expected.as_str(), m.actual.as_str())).collect::<Vec<_>>();
+        assert_eq!(mismatched, vec![("brand", "keyword", "text")]);
+        assert_eq!(diff.to_string(), "  missing field price\n  missing field variant.stock\n  field brand is text but expected keyword\n");
+    }
+
+    #[test]
+    fn an_object_replaced_by_a_leaf_is_a_mismatch() {
+        let expected = json!({ "properties": { "variant": { "properties": { "sku": { "type": "keyword" } } } } });
+        let actual = json!({ "properties": { "variant": { "type": "keyword" } } });
+        let mismatched = diff(&expected, &actual).mismatched;
+        assert_eq!((mismatched[0].expected.as_str(), mismatched[0].actual.as_str()), ("object", "keyword"));
+    }
+
+    async fn rejected_put_mapping(reason: &str) -> ElSearchError {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/products/_mapping"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": { "type": "illegal_argument_exception", "reason": reason },
+                "status": 400
+            })))
+            .mount(&server)
+            .await;
+
+        let mapping = MappingBuilder::new().keyword("price").build();
+        mock_client(&server).put_mapping("products", &mapping).await.unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn changing_a_field_type_is_a_mapping_conflict() {
+        let err = rejected_put_mapping("mapper [price] cannot be changed from type [float] to [keyword]").await;
+        assert!(matches!(&err, ElSearchError::MappingConflict { index, .. } if index == "products"), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn other_illegal_arguments_stay_api_errors() {
+        let err = rejected_put_mapping("unknown parameter [analyzr] on mapper [name] of type [text]").await;
+        assert!(matches!(&err, ElSearchError::Api { status: 400, body } if body.contains("analyzr")), "{:?}", err);
+    }
+
+    #[test]
+    fn conflict_messages_are_recognised() {
+        assert!(is_mapping_conflict("mapper [price] cannot be changed from type [float] to [keyword]"));
+        assert!(is_mapping_conflict("mapper [name.raw] cannot be changed"));
+        assert!(!is_mapping_conflict("unknown parameter [analyzr] on mapper [name] of type [text]"));
+        assert!(!is_mapping_conflict("analyzer [missing] has not been configured in mappings"));
+    }
+}
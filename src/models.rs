@@ -0,0 +1,62 @@
+use serde::{ Deserialize, Serialize };
+use serde_json::{ Map, Value };
+
+use crate::mapping::{ EsMapping, MappingBuilder };
+
+// Unknown fields are rejected so that a misspelled field fails validation instead of
+// being dropped when the document is re-serialized.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Product {
+    pub brand: String,
+    pub category: String,
+    pub description: String,
+    pub name: String,
+    pub price: f64,
+    pub rating: f64,
+}
+
+pub trait Indexable {
+    fn index_name() -> &'static str;
+    fn mapping() -> Value;
+}
+
+impl EsMapping for Product {
+    fn es_mapping() -> MappingBuilder {
+        MappingBuilder::new()
+            .text("name", "standard")
+            .text("description", "standard")
+            .keyword("category")
+            .keyword("brand")
+            .double("price")
+            .double("rating")
+    }
+}
+
+impl Indexable for Product {
+    fn index_name() -> &'static str {
+        "products"
+    }
+
+    fn mapping() -> Value {
+        get_product_mapping()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompositeBucket {
+    pub key: Map<String, Value>,
+    pub doc_count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FieldDataEntry {
+    pub id: String,
+    pub node: String,
+    pub field: String,
+    pub size: String,
+}
+
+pub fn get_product_mapping() -> Value {
+    Product::es_mapping().build_index_body()
+}
@@ -0,0 +1,161 @@
+use elasticsearch::{ Elasticsearch, OpenPointInTimeParts };
+use serde_json::{ json, Value };
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+
+pub const DEFAULT_KEEP_ALIVE: &str = "1m";
+
+async fn close_point_in_time(client: &Elasticsearch, pit_id: &str) -> Result<(), ElSearchError> {
+    let body = json!({ "id": pit_id });
+    Call::cluster("close_point_in_time")
+        .send_checked(Some(&body), client
+            .close_point_in_time()
+            .body(&body)
+            .send())
+        .await?;
+    Ok(())
+}
+
+// Closes a point in time that is still open when its owner goes away, e.g. a
+// paginated stream dropped before it was fully drained. This is best effort: the close
+// is spawned on the current Tokio runtime and its result is not observed, and without
+// a runtime nothing is sent and the point in time lives until its keep_alive runs out.
+// Callers that can should prefer the explicit async `close`.
+pub(crate) struct PitCleanup {
+    client: Elasticsearch,
+    pub(crate) id: Option<String>,
+}
+
+impl PitCleanup {
+    pub(crate) fn new(client: &Elasticsearch) -> Self {
+        PitCleanup {
+            client: client.clone(),
+            id: None,
+        }
+    }
+
+    pub(crate) async fn close(&mut self) -> Result<(), ElSearchError> {
+        match self.id.take() {
+            Some(pit_id) => close_point_in_time(&self.client, &pit_id).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for PitCleanup {
+    fn drop(&mut self) {
+        let Some(pit_id) = self.id.take() else { return };
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let client = self.client.clone();
+                handle.spawn(async move {
+                    let _ = close_point_in_time(&client, &pit_id).await;
+                });
+            }
+            Err(_) => tracing::warn!(pit_id = %pit_id, "point in time dropped outside a Tokio runtime, it stays open until its keep_alive expires"),
+        }
+    }
+}
+
+// An open point in time that is closed in the background when dropped, as long as
+// it is dropped inside a Tokio runtime. Prefer calling `close`, which closes it
+// explicitly and reports whether that worked.
+pub struct PitGuard {
+    cleanup: PitCleanup,
+}
+
+impl PitGuard {
+    pub fn id(&self) -> &str {
+        self.cleanup.id.as_deref().unwrap_or_default()
+    }
+
+    pub async fn close(mut self) -> Result<(), ElSearchError> {
+        self.cleanup.close().await
+    }
+}
+
+impl ElSearch {
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn open_pit(&self, index_name: &str, keep_alive: &str) -> Result<String, ElSearchError> {
+        let response = Call::new("open_pit", index_name)
+            .send_checked(None, self.client
+                .open_point_in_time(OpenPointInTimeParts::Index(&[index_name]))
+                .keep_alive(keep_alive)
+                .send())
+            .await?;
+        let resp_body = response.json::<Value>().await?;
+
+        resp_body["id"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| ElSearchError::InvalidResponse("point in time response has no id".to_string()))
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn open_pit_guarded(&self, index_name: &str, keep_alive: &str) -> Result<PitGuard, ElSearchError> {
+        let mut cleanup = PitCleanup::new(&self.client);
+        cleanup.id = Some(self.open_pit(index_name, keep_alive).await?);
+        Ok(PitGuard { cleanup })
+    }
+
+    #[instrument(skip_all)]
+    pub async fn close_pit(&self, pit_id: &str) -> Result<(), ElSearchError> {
+        close_point_in_time(&self.client, pit_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::{ mock_client, received, CapturedLogs };
+
+    #[tokio::test]
+    async fn dropping_a_guard_closes_the_point_in_time() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_pit"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "pit-1" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/_pit"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "succeeded": true, "num_freed": 1 })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let guard = mock_client(&server).open_pit_guarded("products", DEFAULT_KEEP_ALIVE).await.unwrap();
+        drop(guard);
+
+        let mut closes = Vec::new();
+        for _ in 0..50 {
+            closes = received(&server).await.into_iter().filter(|request| request.method.as_str() == "DELETE").collect();
+            if !closes.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(closes.len(), 1, "the point in time was not closed");
+        let body: Value = serde_json::from_slice(&closes[0].body).unwrap();
+        assert_eq!(body, json!({ "id": "pit-1" }));
+    }
+
+    #[test]
+    fn dropping_outside_a_runtime_warns_with_the_pit_id() {
+        let logs = CapturedLogs::default();
+        let _guard = logs.install();
+
+        let mut cleanup = PitCleanup::new(&Elasticsearch::default());
+        cleanup.id = Some("pit-1".to_string());
+        drop(cleanup);
+
+        let lines = logs.lines();
+        assert!(lines.iter().any(|line| line.contains("WARN") && line.contains("pit_id=pit-1")), "{:?}", lines);
+    }
+}
@@ -0,0 +1,253 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlanHint {
+    pub risk: RiskLevel,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlanReport {
+    pub index: String,
+    pub valid: bool,
+    pub explanation: Option<String>,
+    pub count: Option<i64>,
+    pub hints: Vec<PlanHint>,
+    pub risk: RiskLevel,
+}
+
+impl PlanReport {
+    pub fn assemble(index_name: &str, validate_resp: &Value, count_resp: &Value, mut hints: Vec<PlanHint>) -> Self {
+        let valid = validate_resp["valid"].as_bool().unwrap_or(false);
+        let explanation = validate_resp["explanations"]
+            .as_array()
+            .and_then(|explanations| explanations.first())
+            .and_then(|first| first["explanation"].as_str().or_else(|| first["error"].as_str()))
+            .map(String::from)
+            .or_else(|| validate_resp["error"].as_str().map(String::from));
+
+        if !valid {
+            hints.push(PlanHint {
+                risk: RiskLevel::High,
+                message: "query did not pass _validate/query and will fail".to_string(),
+            });
+        }
+
+        let risk = hints.iter().map(|hint| hint.risk).max().unwrap_or(RiskLevel::Low);
+
+        PlanReport {
+            index: index_name.to_string(),
+            valid,
+            explanation,
+            count: count_resp["count"].as_i64(),
+            hints,
+            risk,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+impl fmt::Display for PlanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Plan for index {}: risk {:?}", self.index, self.risk)?;
+        writeln!(f, "  valid: {}", self.valid)?;
+        if let Some(explanation) = &self.explanation {
+            writeln!(f, "  explanation: {}", explanation)?;
+        }
+        match self.count {
+            Some(count) => writeln!(f, "  matching documents: {}", count)?,
+            None => writeln!(f, "  matching documents: unknown")?,
+        }
+        for hint in &self.hints {
+            writeln!(f, "  [{:?}] {}", hint.risk, hint.message)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn lint_query(body: &Value, field_caps: &Value) -> Vec<PlanHint> {
+    let mut hints = Vec::new();
+
+    if let Some(size) = body["size"].as_i64() {
+        if size > 10_000 {
+            hints.push(PlanHint { risk: RiskLevel::High, message: format!("size {} exceeds the 10000 result window", size) });
+        } else if size > 1_000 {
+            hints.push(PlanHint { risk: RiskLevel::Medium, message: format!("size {} fetches a large page of hits", size) });
+        }
+    }
+
+    lint_value(body, field_caps, &mut hints);
+    hints
+}
+
+fn lint_value(value: &Value, field_caps: &Value, hints: &mut Vec<PlanHint>) {
+    match value {
+        Value::Object(map) => {
+            for (key, inner) in map {
+                match key.as_str() {
+                    "wildcard" | "regexp" => lint_pattern_query(key, inner, hints),
+                    "query_string" => {
+                        let query = inner["query"].as_str().unwrap_or_default();
+                        if query.starts_with('*') || query.starts_with('?') {
+                            hints.push(PlanHint { risk: RiskLevel::High, message: format!("query_string \"{}\" starts with a wildcard", query) });
+                        }
+                    }
+                    "script" | "script_score" | "scripted_metric" => {
+                        hints.push(PlanHint { risk: RiskLevel::Medium, message: format!("{} is evaluated per document", key) });
+                    }
+                    "aggs" | "aggregations" => lint_aggregations(inner, field_caps, hints),
+                    _ => {}
+                }
+                lint_value(inner, field_caps, hints);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                lint_value(item, field_caps, hints);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn lint_pattern_query(kind: &str, query: &Value, hints: &mut Vec<PlanHint>) {
+    let Some(fields) = query.as_object() else { return };
+
+    for (field, spec) in fields {
+        let pattern = spec.as_str()
+            .or_else(|| spec["value"].as_str())
+            .unwrap_or_default();
+        let leading = match kind {
+            "regexp" => pattern.starts_with(".*") || pattern.starts_with(".+"),
+            _ => pattern.starts_with('*') || pattern.starts_with('?'),
+        };
+        if leading {
+            hints.push(PlanHint { risk: RiskLevel::High, message: format!("{} on {} has a leading wildcard \"{}\"", kind, field, pattern) });
+        }
+    }
+}
+
+// True when any index of a field_caps response maps `field` as text.
+pub(crate) fn is_text_field(field_caps: &Value, field: &str) -> bool {
+    field_caps["fields"][field]
+        .as_object()
+        .map(|types| types.contains_key("text"))
+        .unwrap_or(false)
+}
+
+fn lint_aggregations(aggs: &Value, field_caps: &Value, hints: &mut Vec<PlanHint>) {
+    let Some(aggs) = aggs.as_object() else { return };
+
+    for (name, agg) in aggs {
+        for kind in ["terms", "cardinality", "significant_terms", "rare_terms"] {
+            let Some(field) = agg[kind]["field"].as_str() else { continue };
+            if is_text_field(field_caps, field) {
+                hints.push(PlanHint { risk: RiskLevel::High, message: format!("aggregation {} runs {} over text field {}", name, kind, field) });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn risks(body: &Value, field_caps: &Value) -> Vec<RiskLevel> {
+        lint_query(body, field_caps).iter().map(|hint| hint.risk).collect()
+    }
+
+    #[test]
+    fn plain_queries_carry_no_hints() {
+        let body = json!({ "query": { "match": { "name": "laptop" } }, "size": 10 });
+        assert!(risks(&body, &json!({})).is_empty());
+    }
+
+    #[test]
+    fn large_pages_are_medium_and_deep_pages_high() {
+        assert_eq!(risks(&json!({ "size": 1_000 }), &json!({})), vec![]);
+        assert_eq!(risks(&json!({ "size": 1_001 }), &json!({})), vec![RiskLevel::Medium]);
+        assert_eq!(risks(&json!({ "size": 10_001 }), &json!({})), vec![RiskLevel::High]);
+    }
+
+    #[test]
+    fn leading_wildcards_are_high() {
+        let nested = json!({ "query": { "bool": { "must": [
+            { "wildcard": { "name": { "value": "*top" } } },
+            { "wildcard": { "brand": "acme*" } }
+        ] } } });
+        assert_eq!(risks(&nested, &json!({})), vec![RiskLevel::High]);
+
+        assert_eq!(risks(&json!({ "query": { "regexp": { "name": ".*top" } } }), &json!({})), vec![RiskLevel::High]);
+        assert_eq!(risks(&json!({ "query": { "regexp": { "name": "lap.*" } } }), &json!({})), vec![]);
+        assert_eq!(risks(&json!({ "query": { "query_string": { "query": "?aptop" } } }), &json!({})), vec![RiskLevel::High]);
+    }
+
+    #[test]
+    fn scripts_are_medium() {
+        let body = json!({ "query": { "script_score": {
+            "query": { "match_all": {} },
+            "script": { "source": "doc['price'].value" }
+        } } });
+        assert_eq!(risks(&body, &json!({})), vec![RiskLevel::Medium, RiskLevel::Medium]);
+    }
+
+    #[test]
+    fn aggregating_text_fields_is_high() {
+        let field_caps = json!({ "fields": {
+            "description": { "text": { "type": "text" } },
+            "category": { "keyword": { "type": "keyword" } }
+        } });
+        let body = json!({ "size": 0, "aggs": {
+            "by_description": { "terms": { "field": "description" } },
+            "by_category": { "terms": { "field": "category" } },
+            "distinct_descriptions": { "cardinality": { "field": "description" } }
+        } });
+        assert_eq!(risks(&body, &field_caps), vec![RiskLevel::High, RiskLevel::High]);
+    }
+
+    #[test]
+    fn assemble_takes_the_highest_hint_risk() {
+        let validate_resp = json!({ "valid": true, "explanations": [{ "explanation": "name:laptop" }] });
+        let hints = lint_query(&json!({ "size": 2_000, "query": { "wildcard": { "name": "*top" } } }), &json!({}));
+
+        let report = PlanReport::assemble("products", &validate_resp, &json!({ "count": 42 }), hints);
+        assert!(report.valid);
+        assert_eq!(report.explanation.as_deref(), Some("name:laptop"));
+        assert_eq!(report.count, Some(42));
+        assert_eq!(report.risk, RiskLevel::High);
+        assert_eq!(report.to_json()["risk"], json!("high"));
+    }
+
+    #[test]
+    fn assemble_marks_invalid_queries_high() {
+        let validate_resp = json!({ "valid": false, "explanations": [{ "error": "unknown field [nmae]" }] });
+
+        let report = PlanReport::assemble("products", &validate_resp, &Value::Null, Vec::new());
+        assert!(!report.valid);
+        assert_eq!(report.explanation.as_deref(), Some("unknown field [nmae]"));
+        assert_eq!(report.count, None);
+        assert_eq!(report.risk, RiskLevel::High);
+    }
+
+    #[test]
+    fn assemble_without_hints_is_low() {
+        let report = PlanReport::assemble("products", &json!({ "valid": true }), &json!({ "count": 0 }), Vec::new());
+        assert_eq!(report.risk, RiskLevel::Low);
+        assert!(report.hints.is_empty());
+    }
+}
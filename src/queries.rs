@@ -0,0 +1,237 @@
+use serde_json::{ json, Map, Value };
+
+use crate::error::ElSearchError;
+
+// Top-level keys of a search request body, as opposed to the name of a query clause.
+const SEARCH_BODY_KEYS: &[&str] = &[
+    "query", "size", "from", "sort", "aggs", "aggregations", "_source", "fields", "docvalue_fields",
+    "stored_fields", "track_total_hits", "track_scores", "min_score", "post_filter", "highlight",
+    "collapse", "runtime_mappings", "script_fields", "timeout", "terminate_after", "explain",
+    "version", "seq_no_primary_term", "profile", "search_after", "pit", "rescore", "suggest",
+    "indices_boost", "knn", "stats",
+];
+
+// Accepts either a full search body (`{"query": {...}, "size": 5}`, where `{}` means
+// everything) or a bare query clause (`{"term": {...}}`) and returns a search body.
+// Anything else is rejected rather than guessed at, so a mistyped query never turns
+// into a search over the whole index.
+pub(crate) fn to_search_body(query: &Value) -> Result<Value, ElSearchError> {
+    let Some(fields) = query.as_object() else {
+        return Err(ElSearchError::InvalidQuery(format!("expected a JSON object, got {}", query)));
+    };
+    if fields.keys().all(|key| SEARCH_BODY_KEYS.contains(&key.as_str())) {
+        return Ok(query.clone());
+    }
+    if fields.len() == 1 {
+        return Ok(json!({ "query": query }));
+    }
+    let unknown = fields.keys().filter(|key| !SEARCH_BODY_KEYS.contains(&key.as_str())).cloned().collect::<Vec<_>>();
+    Err(ElSearchError::InvalidQuery(format!("neither a search body nor a single query clause (unexpected keys: {})", unknown.join(", "))))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct QueryBuilder {
+    must: Vec<Value>,
+    filter: Vec<Value>,
+    sort: Vec<Value>,
+    from: Option<i64>,
+    size: Option<i64>,
+    profile: bool,
+}
+
+pub type SearchQuery = QueryBuilder;
+
+impl QueryBuilder {
+    pub fn new() -> Self {
+        QueryBuilder::default()
+    }
+
+    pub fn multi_match(mut self, query: &str, fields: &[&str]) -> Self {
+        self.must.push(json!({
+            "multi_match": {
+                "query": query,
+                "fields": fields
+            }
+        }));
+        self
+    }
+
+    pub fn filter_term(mut self, field: &str, value: impl Into<Value>) -> Self {
+        self.filter.push(json!({
+            "term": {
+                field: value.into()
+            }
+        }));
+        self
+    }
+
+    pub fn range(mut self, field: &str, gte: Option<f64>, lte: Option<f64>) -> Self {
+        let mut bounds = Map::new();
+        if let Some(gte) = gte {
+            bounds.insert("gte".to_string(), json!(gte));
+        }
+        if let Some(lte) = lte {
+            bounds.insert("lte".to_string(), json!(lte));
+        }
+        if !bounds.is_empty() {
+            self.filter.push(json!({
+                "range": {
+                    field: bounds
+                }
+            }));
+        }
+        self
+    }
+
+    pub fn price_range(self, gte: Option<f64>, lte: Option<f64>) -> Self {
+        self.range("price", gte, lte)
+    }
+
+    pub fn rating_range(self, gte: Option<f64>, lte: Option<f64>) -> Self {
+        self.range("rating", gte, lte)
+    }
+
+    pub fn sort_asc(mut self, field: &str) -> Self {
+        self.sort.push(json!({ field: "asc" }));
+        self
+    }
+
+    pub fn sort_desc(mut self, field: &str) -> Self {
+        self.sort.push(json!({ field: "desc" }));
+        self
+    }
+
+    pub fn from(mut self, from: i64) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    pub fn size(mut self, size: i64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn profile(mut self) -> Self {
+        self.profile = true;
+        self
+    }
+
+    pub fn build_query(&self) -> Value {
+        if self.must.is_empty() && self.filter.is_empty() {
+            return json!({ "match_all": {} });
+        }
+        if self.must.len() == 1 && self.filter.is_empty() {
+            return self.must[0].clone();
+        }
+
+        let mut bool_query = Map::new();
+        if !self.must.is_empty() {
+            bool_query.insert("must".to_string(), Value::Array(self.must.clone()));
+        }
+        if !self.filter.is_empty() {
+            bool_query.insert("filter".to_string(), Value::Array(self.filter.clone()));
+        }
+        json!({ "bool": bool_query })
+    }
+
+    pub fn build(&self) -> Value {
+        let mut body = json!({ "query": self.build_query() });
+        if !self.sort.is_empty() {
+            body["sort"] = Value::Array(self.sort.clone());
+        }
+        if let Some(from) = self.from {
+            body["from"] = json!(from);
+        }
+        if let Some(size) = self.size {
+            body["size"] = json!(size);
+        }
+        if self.profile {
+            body["profile"] = json!(true);
+        }
+        body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::{ mock_client, received };
+
+    #[test]
+    fn empty_builder_matches_everything() {
+        assert_eq!(QueryBuilder::new().build(), json!({ "query": { "match_all": {} } }));
+    }
+
+    #[test]
+    fn a_single_clause_is_not_wrapped_in_bool() {
+        let body = QueryBuilder::new().multi_match("laptop", &["name", "description"]).build();
+        assert_eq!(body, json!({
+            "query": { "multi_match": { "query": "laptop", "fields": ["name", "description"] } }
+        }));
+    }
+
+    #[test]
+    fn filters_and_must_clauses_nest_under_bool() {
+        let body = QueryBuilder::new()
+            .multi_match("laptop", &["name"])
+            .filter_term("brand", "acme")
+            .price_range(Some(100.0), None)
+            .rating_range(None, None)
+            .sort_desc("price")
+            .sort_asc("name")
+            .from(20)
+            .size(10)
+            .profile()
+            .build();
+
+        assert_eq!(body, json!({
+            "query": {
+                "bool": {
+                    "must": [{ "multi_match": { "query": "laptop", "fields": ["name"] } }],
+                    "filter": [
+                        { "term": { "brand": "acme" } },
+                        { "range": { "price": { "gte": 100.0 } } }
+                    ]
+                }
+            },
+            "sort": [{ "price": "desc" }, { "name": "asc" }],
+            "from": 20,
+            "size": 10,
+            "profile": true
+        }));
+    }
+
+    #[test]
+    fn filters_alone_omit_must() {
+        let query = QueryBuilder::new().filter_term("in_stock", true).range("rating", Some(4.0), Some(5.0)).build_query();
+        assert_eq!(query, json!({
+            "bool": {
+                "filter": [
+                    { "term": { "in_stock": true } },
+                    { "range": { "rating": { "gte": 4.0, "lte": 5.0 } } }
+                ]
+            }
+        }));
+    }
+
+    #[tokio::test]
+    async fn search_sends_the_built_body_unchanged() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "hits": { "total": { "value": 0, "relation": "eq" }, "hits": [] }
+            })))
+            .mount(&server)
+            .await;
+
+        let body = QueryBuilder::new().multi_match("laptop", &["name"]).filter_term("brand", "acme").size(5).build();
+        mock_client(&server).search("products", &body).await.unwrap();
+
+        let requests = received(&server).await;
+        let sent: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(sent, body);
+    }
+}
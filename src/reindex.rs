@@ -0,0 +1,243 @@
+use elasticsearch::tasks::TasksGetParts;
+use serde_json::{ json, Value };
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+
+const REINDEX_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const REINDEX_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+// A reindex running in the background, as returned by `start_reindex`.
+#[derive(Debug, Clone)]
+pub struct ReindexTask {
+    pub task_id: String,
+    pub source_index: String,
+    pub dest_index: String,
+}
+
+// A document the reindex could not write, with the first reason Elasticsearch gave.
+#[derive(Debug, Clone)]
+pub struct ReindexFailure {
+    pub id: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReindexOutcome {
+    pub total: u64,
+    pub created: u64,
+    pub updated: u64,
+    pub version_conflicts: u64,
+    pub failures: Vec<ReindexFailure>,
+}
+
+impl ReindexOutcome {
+    fn from_response_body(resp_body: &Value) -> Self {
+        let failures = resp_body["failures"]
+            .as_array()
+            .map(|failures| failures.iter().map(ReindexFailure::from_failure).collect())
+            .unwrap_or_default();
+        ReindexOutcome {
+            total: resp_body["total"].as_u64().unwrap_or_default(),
+            created: resp_body["created"].as_u64().unwrap_or_default(),
+            updated: resp_body["updated"].as_u64().unwrap_or_default(),
+            version_conflicts: resp_body["version_conflicts"].as_u64().unwrap_or_default(),
+            failures,
+        }
+    }
+}
+
+impl ReindexFailure {
+    // Bulk failures carry the reason under "cause", search failures under "reason".
+    fn from_failure(failure: &Value) -> Self {
+        let reason = failure["cause"]["reason"]
+            .as_str()
+            .or_else(|| failure["reason"]["reason"].as_str())
+            .unwrap_or("no reason given");
+        ReindexFailure {
+            id: failure["id"].as_str().map(String::from),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+fn reindex_body(source_index: &str, dest_index: &str, query: Option<&Value>) -> Value {
+    let mut source = json!({ "index": source_index });
+    if let Some(query) = query {
+        source["query"] = query.clone();
+    }
+    json!({
+        "source": source,
+        "dest": { "index": dest_index }
+    })
+}
+
+impl ElSearch {
+    // `query` is a query clause, e.g. `{"term": {"brand": "acme"}}`, restricting which documents are copied.
+    // The reindex runs as a task that is polled until it finishes, so copying a large
+    // index is not cut off by the client's request timeout.
+    #[instrument(skip_all)]
+    pub async fn reindex(&self, source_index: &str, dest_index: &str, query: Option<&Value>) -> Result<ReindexOutcome, ElSearchError> {
+        let task = self.start_reindex(source_index, dest_index, query).await?;
+        self.wait_for_task(&task, REINDEX_POLL_INTERVAL, REINDEX_TIMEOUT).await
+    }
+
+    // Starts the reindex in the background and returns the task for `wait_for_task`.
+    #[instrument(skip_all)]
+    pub async fn start_reindex(&self, source_index: &str, dest_index: &str, query: Option<&Value>) -> Result<ReindexTask, ElSearchError> {
+        let body = reindex_body(source_index, dest_index, query);
+        let response = Call::cluster("start_reindex")
+            .send_checked(Some(&body), self.client
+                .reindex()
+                .wait_for_completion(false)
+                .body(&body)
+                .send())
+            .await?;
+        let resp_body = response.json::<Value>().await?;
+
+        let task_id = resp_body["task"]
+            .as_str()
+            .ok_or_else(|| ElSearchError::InvalidResponse("reindex response has no task id".to_string()))?;
+        Ok(ReindexTask {
+            task_id: task_id.to_string(),
+            source_index: source_index.to_string(),
+            dest_index: dest_index.to_string(),
+        })
+    }
+
+    // Polls the task until it completes. A task that fails as a whole is a `ReindexFailed`
+    // error; documents that could not be written are listed in the outcome's `failures`.
+    // Gives up with `Timeout` after `timeout`, leaving the task running in the cluster.
+    #[instrument(skip_all, fields(task = %task.task_id))]
+    pub async fn wait_for_task(&self, task: &ReindexTask, poll_interval: Duration, timeout: Duration) -> Result<ReindexOutcome, ElSearchError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let response = Call::cluster("wait_for_task")
+                .send_checked(None, self.client
+                    .tasks()
+                    .get(TasksGetParts::TaskId(&task.task_id))
+                    .send())
+                .await?;
+            let resp_body = response.json::<Value>().await?;
+
+            if resp_body["completed"].as_bool() == Some(true) {
+                if let Some(reason) = resp_body["error"]["reason"].as_str() {
+                    return Err(ElSearchError::ReindexFailed {
+                        source_index: task.source_index.clone(),
+                        dest_index: task.dest_index.clone(),
+                        reason: format!("task {} failed: {}", task.task_id, reason),
+                    });
+                }
+                return Ok(ReindexOutcome::from_response_body(&resp_body["response"]));
+            }
+
+            if Instant::now() + poll_interval > deadline {
+                return Err(ElSearchError::Timeout(format!("task {} did not complete within {:?}", task.task_id, timeout)));
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{ matchers::{ method, path, query_param }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::{ mock_client, received };
+
+    #[tokio::test]
+    async fn reindex_polls_the_task_until_it_completes() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/_reindex"))
+            .and(query_param("wait_for_completion", "false"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "task": "node:7" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/_tasks/node:7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "completed": false })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/_tasks/node:7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "completed": true,
+                "response": { "total": 3, "created": 2, "updated": 1, "version_conflicts": 0, "failures": [] }
+            })))
+            .mount(&server)
+            .await;
+
+        let outcome = mock_client(&server).reindex("products", "products-v2", None).await.unwrap();
+        assert_eq!((outcome.total, outcome.created, outcome.updated, outcome.failures.len()), (3, 2, 1, 0));
+
+        let requests = received(&server).await;
+        assert_eq!(requests.len(), 3);
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body, json!({ "source": { "index": "products" }, "dest": { "index": "products-v2" } }));
+    }
+
+    fn task() -> ReindexTask {
+        ReindexTask { task_id: "node:7".to_string(), source_index: "products".to_string(), dest_index: "products-v2".to_string() }
+    }
+
+    async fn mount_task(server: &MockServer, status: Value) {
+        Mock::given(method("GET"))
+            .and(path("/_tasks/node:7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(status))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn a_failed_task_is_a_reindex_failure() {
+        let server = MockServer::start().await;
+        mount_task(&server, json!({
+            "completed": true,
+            "error": { "type": "index_not_found_exception", "reason": "no such index [products]" }
+        })).await;
+
+        let err = mock_client(&server).wait_for_task(&task(), Duration::from_millis(10), Duration::from_secs(5)).await.unwrap_err();
+        match err {
+            ElSearchError::ReindexFailed { source_index, dest_index, reason } => {
+                assert_eq!((source_index.as_str(), dest_index.as_str()), ("products", "products-v2"));
+                assert!(reason.contains("no such index [products]"), "{}", reason);
+            }
+            other => panic!("expected a reindex failure, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn document_failures_carry_their_reasons() {
+        let server = MockServer::start().await;
+        mount_task(&server, json!({
+            "completed": true,
+            "response": { "total": 2, "created": 1, "failures": [
+                { "index": "products-v2", "id": "3", "cause": { "type": "mapper_parsing_exception", "reason": "failed to parse field [price]" }, "status": 400 }
+            ] }
+        })).await;
+
+        let outcome = mock_client(&server).wait_for_task(&task(), Duration::from_millis(10), Duration::from_secs(5)).await.unwrap();
+        assert_eq!(outcome.failures.len(), 1);
+        assert_eq!(outcome.failures[0].id.as_deref(), Some("3"));
+        assert_eq!(outcome.failures[0].reason, "failed to parse field [price]");
+    }
+
+    #[tokio::test]
+    async fn polling_gives_up_after_the_timeout() {
+        let server = MockServer::start().await;
+        mount_task(&server, json!({ "completed": false })).await;
+
+        let err = mock_client(&server).wait_for_task(&task(), Duration::from_millis(10), Duration::from_millis(50)).await.unwrap_err();
+        assert!(matches!(&err, ElSearchError::Timeout(reason) if reason.contains("node:7")), "{:?}", err);
+        let polls = received(&server).await.len();
+        assert!((1..=6).contains(&polls), "polled {} times", polls);
+    }
+}
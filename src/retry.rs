@@ -0,0 +1,284 @@
+use elasticsearch::http::{ response::Response, StatusCode };
+use serde_json::Value;
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::future::Future;
+use std::hash::{ BuildHasher, Hasher };
+use std::io;
+use std::time::Duration;
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    // Exponential backoff for the given retry (1-based), capped at max_backoff.
+    // With jitter the delay is picked uniformly from the upper half of that range.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let backoff = self.initial_backoff.saturating_mul(factor).min(self.max_backoff);
+        if !self.jitter {
+            return backoff;
+        }
+
+        let random = RandomState::new().build_hasher().finish();
+        let half = backoff / 2;
+        half + half.mul_f64((random % 1000) as f64 / 1000.0)
+    }
+}
+
+pub(crate) fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE
+}
+
+// The connection was never established, so the request cannot have reached Elasticsearch.
+fn is_connect_error(err: &elasticsearch::Error) -> bool {
+    let mut source: Option<&(dyn Error + 'static)> = Some(err);
+    while let Some(err) = source {
+        if let Some(io_err) = err.downcast_ref::<io::Error>() {
+            if matches!(io_err.kind(), io::ErrorKind::ConnectionRefused | io::ErrorKind::AddrNotAvailable | io::ErrorKind::NotConnected) {
+                return true;
+            }
+        }
+        source = err.source();
+    }
+    false
+}
+
+// A request that timed out may still have been applied, so timeouts are only retried
+// when sending the request twice has the same effect as sending it once.
+fn is_retryable_transport_error(err: &elasticsearch::Error, idempotent: bool) -> bool {
+    is_connect_error(err) || (idempotent && err.is_timeout())
+}
+
+fn give_up(retries: u32, last: ElSearchError) -> ElSearchError {
+    if retries == 0 {
+        return last;
+    }
+    ElSearchError::RetriesExhausted {
+        retries,
+        last: Box::new(last),
+    }
+}
+
+async fn retry_with_policy<F, Fut>(retry_policy: RetryPolicy, idempotent: bool, call: Call<'_>, body: Option<&Value>, mut send: F) -> Result<Response, ElSearchError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Response, elasticsearch::Error>>,
+{
+    let max_attempts = retry_policy.max_attempts.max(1);
+    let mut retries = 0;
+
+    loop {
+        let exhausted = retries + 1 >= max_attempts;
+        match call.send(body, send()).await {
+            Ok(response) if is_retryable_status(response.status_code()) && !exhausted => {}
+            Ok(response) => return call.ensure_success(response).await.map_err(|err| give_up(retries, err)),
+            Err(ElSearchError::Transport(err)) if is_retryable_transport_error(&err, idempotent) && !exhausted => {}
+            Err(err) => return Err(give_up(retries, err)),
+        }
+
+        retries += 1;
+        let backoff = retry_policy.backoff(retries);
+        call.log_retry(retries, backoff.as_millis() as u64);
+        tokio::time::sleep(backoff).await;
+    }
+}
+
+impl ElSearch {
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    // Runs `op` with the client's backoff settings but a caller-chosen number of attempts.
+    // `op` may not be idempotent, so timeouts are not retried.
+    #[instrument(skip_all)]
+    pub async fn with_retry<F, Fut>(&self, max_attempts: u32, op: F) -> Result<Response, ElSearchError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<Response, elasticsearch::Error>>,
+    {
+        let retry_policy = RetryPolicy {
+            max_attempts,
+            ..self.retry_policy
+        };
+        retry_with_policy(retry_policy, false, Call::cluster("with_retry"), None, op).await
+    }
+
+    pub(crate) async fn send_with_retry<F, Fut>(&self, call: Call<'_>, body: Option<&Value>, send: F) -> Result<Response, ElSearchError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<Response, elasticsearch::Error>>,
+    {
+        retry_with_policy(self.retry_policy, true, call, body, send).await
+    }
+
+    // For requests that must not be applied twice, such as indexing with a generated id.
+    pub(crate) async fn send_non_idempotent_with_retry<F, Fut>(&self, call: Call<'_>, body: Option<&Value>, send: F) -> Result<Response, ElSearchError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<Response, elasticsearch::Error>>,
+    {
+        retry_with_policy(self.retry_policy, false, call, body, send).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{ json, Value };
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::fixtures::{ bulk_response, ItemOutcome };
+    use crate::test_support::{ fast_retry_policy, mock_client, ndjson_lines, received };
+
+    // Answers the first `failures` requests to `route` with `status`, the rest with `body`.
+    async fn failing_first(server: &MockServer, http_method: &str, route: &str, failures: u64, status: u16, body: Value) {
+        Mock::given(method(http_method))
+            .and(path(route))
+            .respond_with(ResponseTemplate::new(status))
+            .up_to_n_times(failures)
+            .mount(server)
+            .await;
+        Mock::given(method(http_method))
+            .and(path(route))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(server)
+            .await;
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let retry_policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(350),
+            jitter: false,
+        };
+        let backoffs = (1..=4).map(|retry| retry_policy.backoff(retry).as_millis()).collect::<Vec<_>>();
+        assert_eq!(backoffs, vec![100, 200, 350, 350]);
+    }
+
+    #[tokio::test]
+    async fn unavailable_is_retried_until_success() {
+        let server = MockServer::start().await;
+        failing_first(&server, "POST", "/products/_search", 2, 503, json!({ "hits": { "hits": [] } })).await;
+
+        mock_client(&server).search("products", &json!({})).await.unwrap();
+        assert_eq!(received(&server).await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let server = MockServer::start().await;
+        failing_first(&server, "POST", "/products/_search", 1, 400, json!({})).await;
+
+        let err = mock_client(&server).search("products", &json!({})).await.unwrap_err();
+        assert!(matches!(err, ElSearchError::Api { status: 400, .. }), "{:?}", err);
+        assert_eq!(received(&server).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let server = MockServer::start().await;
+        failing_first(&server, "POST", "/products/_search", 10, 429, json!({})).await;
+
+        let err = mock_client(&server).search("products", &json!({})).await.unwrap_err();
+        match err {
+            ElSearchError::RetriesExhausted { retries, last } => {
+                assert_eq!(retries, 2);
+                assert!(matches!(*last, ElSearchError::Api { status: 429, .. }), "{:?}", last);
+            }
+            other => panic!("expected retries to be exhausted, got {:?}", other),
+        }
+        assert_eq!(received(&server).await.len(), fast_retry_policy().max_attempts as usize);
+    }
+
+    #[tokio::test]
+    async fn rejected_bulk_items_are_resubmitted_alone() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_bulk"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(bulk_response(vec![
+                ItemOutcome::Created,
+                ItemOutcome::failed(429, "es_rejected_execution_exception", "queue is full"),
+                ItemOutcome::Created,
+            ])))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/products/_bulk"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(bulk_response(vec![ItemOutcome::Created])))
+            .mount(&server)
+            .await;
+
+        let documents = vec![json!({ "n": 0 }), json!({ "n": 1 }), json!({ "n": 2 })];
+        let report = mock_client(&server).bulk_create_checked("products", documents).await.unwrap();
+
+        assert_eq!(report.successful, 3);
+        assert!(report.failed.is_empty());
+        let requests = received(&server).await;
+        assert_eq!(requests.len(), 2);
+        assert_eq!(ndjson_lines(&requests[1]), vec![json!({ "create": {} }), json!({ "n": 1 })]);
+    }
+
+    #[tokio::test]
+    async fn refused_connections_are_retried() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let es = ElSearch::new_from_localhost(&url).unwrap().with_retry_policy(fast_retry_policy());
+        let err = es.add_document("products", &json!({ "name": "Laptop" })).await.unwrap_err();
+        assert!(matches!(err, ElSearchError::RetriesExhausted { retries: 2, .. }), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn timeouts_are_only_retried_for_idempotent_requests() {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})).set_delay(Duration::from_millis(500)))
+            .mount(&server)
+            .await;
+        let es = ElSearch::new_from_localhost_with_timeout(&server.uri(), Duration::from_millis(50))
+            .unwrap()
+            .with_retry_policy(fast_retry_policy());
+
+        let err = es.add_document("products", &json!({ "name": "Laptop" })).await.unwrap_err();
+        assert!(matches!(&err, ElSearchError::Transport(err) if err.is_timeout()), "{:?}", err);
+        assert_eq!(received(&server).await.len(), 1);
+
+        let err = es.search("products", &json!({})).await.unwrap_err();
+        assert!(matches!(err, ElSearchError::RetriesExhausted { retries: 2, .. }), "{:?}", err);
+        assert_eq!(received(&server).await.len(), 1 + 3);
+    }
+}
@@ -0,0 +1,124 @@
+use elasticsearch::{ ClearScrollParts, ScrollParts, SearchParts };
+use serde::de::DeserializeOwned;
+use serde_json::{ json, Value };
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+
+pub const SCROLL_KEEP_ALIVE: &str = "1m";
+
+impl ElSearch {
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn scroll_all<T: DeserializeOwned>(&self, index_name: &str, query: &Value, batch_size: i64) -> Result<Vec<T>, ElSearchError> {
+        if batch_size <= 0 {
+            return Err(ElSearchError::InvalidArgument(format!("batch_size ({}) must be positive", batch_size)));
+        }
+
+        let response = Call::new("scroll_all", index_name)
+            .send_checked(Some(query), self.client
+                .search(SearchParts::Index(&[index_name]))
+                .scroll(SCROLL_KEEP_ALIVE)
+                .size(batch_size)
+                .body(query)
+                .send())
+            .await?;
+        let resp_body = response.json::<Value>().await?;
+
+        let mut scroll_id = resp_body["_scroll_id"].as_str().map(String::from);
+        let result = self.drain_scroll(index_name, resp_body, &mut scroll_id).await;
+
+        if let Some(scroll_id) = scroll_id {
+            let cleared = self.clear_scroll(&scroll_id).await;
+            if result.is_ok() {
+                cleared?;
+            }
+        }
+        result
+    }
+
+    async fn drain_scroll<T: DeserializeOwned>(&self, index_name: &str, mut resp_body: Value, scroll_id: &mut Option<String>) -> Result<Vec<T>, ElSearchError> {
+        let mut documents = Vec::new();
+
+        loop {
+            let hits = resp_body["hits"]["hits"]
+                .as_array()
+                .ok_or_else(|| ElSearchError::InvalidResponse("scroll response has no hits.hits array".to_string()))?;
+            if hits.is_empty() {
+                return Ok(documents);
+            }
+
+            for hit in hits {
+                documents.push(serde_json::from_value(hit["_source"].clone())?);
+            }
+
+            let current_id = scroll_id
+                .clone()
+                .ok_or_else(|| ElSearchError::InvalidResponse("scroll response has no _scroll_id".to_string()))?;
+            let response = Call::new("drain_scroll", index_name)
+                .send_checked(None, self.client
+                    .scroll(ScrollParts::ScrollId(&current_id))
+                    .scroll(SCROLL_KEEP_ALIVE)
+                    .send())
+                .await?;
+            resp_body = response.json::<Value>().await?;
+
+            if let Some(next_id) = resp_body["_scroll_id"].as_str() {
+                *scroll_id = Some(next_id.to_string());
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    pub async fn clear_scroll(&self, scroll_id: &str) -> Result<(), ElSearchError> {
+        let body = json!({ "scroll_id": [scroll_id] });
+        Call::cluster("clear_scroll")
+            .send_checked(Some(&body), self.client
+                .clear_scroll(ClearScrollParts::None)
+                .body(&body)
+                .send())
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::{ mock_client, received };
+
+    #[tokio::test]
+    async fn a_malformed_hit_still_clears_the_scroll() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "_scroll_id": "scroll-1",
+                "hits": { "hits": [
+                    { "_id": "a", "_source": { "name": "Laptop" } },
+                    { "_id": "b", "_source": { "name": 42 } }
+                ] }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/_search/scroll"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "succeeded": true, "num_freed": 1 })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server).scroll_all::<HashMap<String, String>>("products", &json!({}), 10).await.unwrap_err();
+        assert!(matches!(err, ElSearchError::Serde(_)), "{:?}", err);
+
+        let requests = received(&server).await;
+        let clear = requests.iter().find(|request| request.method.as_str() == "DELETE").unwrap();
+        let body: Value = serde_json::from_slice(&clear.body).unwrap();
+        assert_eq!(body, json!({ "scroll_id": ["scroll-1"] }));
+        assert!(requests.iter().all(|request| !request.url.path().starts_with("/_search/scroll/")), "no further page is fetched");
+    }
+}
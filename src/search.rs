@@ -0,0 +1,715 @@
+use elasticsearch::{ http::{ headers::HeaderMap, request::JsonBody, Method, StatusCode }, CountParts, SearchParts };
+use futures::stream::{ self, Stream };
+use serde::{ de::DeserializeOwned, Deserialize };
+use serde_json::{ json, Value };
+use std::collections::{ HashMap, VecDeque };
+use std::fmt;
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::{ read_json, ElSearchError };
+use crate::logging::Call;
+use crate::pit::{ PitCleanup, DEFAULT_KEEP_ALIVE };
+use crate::queries::to_search_body;
+
+// One or more index names or patterns, e.g. "products", ["products-2024-01", "products-2024-02"]
+// or "products-*".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexSelection {
+    names: Vec<String>,
+}
+
+impl IndexSelection {
+    pub fn names(&self) -> Vec<&str> {
+        self.names.iter().map(String::as_str).collect()
+    }
+
+    // No index names at all; searching with it would hit every index in the cluster.
+    pub fn is_empty(&self) -> bool {
+        self.names.iter().all(String::is_empty)
+    }
+
+    // Whether the selection may legitimately resolve to several or no indices.
+    pub fn is_multi_index(&self) -> bool {
+        self.names.len() != 1 || self.names[0].contains(['*', ','])
+    }
+}
+
+impl fmt::Display for IndexSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.names.join(","))
+    }
+}
+
+impl From<&str> for IndexSelection {
+    fn from(name: &str) -> Self {
+        IndexSelection { names: vec![name.to_string()] }
+    }
+}
+
+impl From<&String> for IndexSelection {
+    fn from(name: &String) -> Self {
+        IndexSelection::from(name.as_str())
+    }
+}
+
+impl From<String> for IndexSelection {
+    fn from(name: String) -> Self {
+        IndexSelection { names: vec![name] }
+    }
+}
+
+impl From<&[&str]> for IndexSelection {
+    fn from(names: &[&str]) -> Self {
+        IndexSelection { names: names.iter().map(|name| name.to_string()).collect() }
+    }
+}
+
+impl<const N: usize> From<&[&str; N]> for IndexSelection {
+    fn from(names: &[&str; N]) -> Self {
+        IndexSelection::from(&names[..])
+    }
+}
+
+impl From<Vec<String>> for IndexSelection {
+    fn from(names: Vec<String>) -> Self {
+        IndexSelection { names }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TotalHits {
+    pub value: u64,
+    pub relation: String,
+}
+
+#[derive(Debug)]
+pub struct Hit<T> {
+    pub id: String,
+    pub score: Option<f64>,
+    pub source: T,
+}
+
+#[derive(Debug)]
+pub struct HitError {
+    pub id: String,
+    pub reason: String,
+}
+
+#[derive(Debug)]
+pub struct SearchResult<T> {
+    pub took: u64,
+    pub total: Option<TotalHits>,
+    pub max_score: Option<f64>,
+    pub hits: Vec<Hit<T>>,
+    pub failures: Vec<HitError>,
+    pub profile: Option<Value>,
+}
+
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub has_more: bool,
+}
+
+impl<T: DeserializeOwned> SearchResult<T> {
+    pub fn from_response_body(resp_body: Value) -> Result<Self, ElSearchError> {
+        let raw_hits = resp_body["hits"]["hits"]
+            .as_array()
+            .ok_or_else(|| ElSearchError::InvalidResponse("search response has no hits.hits array".to_string()))?;
+
+        let mut hits = Vec::with_capacity(raw_hits.len());
+        let mut failures = Vec::new();
+
+        for raw_hit in raw_hits {
+            let id = raw_hit["_id"].as_str().unwrap_or_default().to_string();
+            match serde_json::from_value::<T>(raw_hit["_source"].clone()) {
+                Ok(source) => hits.push(Hit {
+                    id,
+                    score: raw_hit["_score"].as_f64(),
+                    source,
+                }),
+                Err(err) => failures.push(HitError {
+                    id,
+                    reason: err.to_string(),
+                }),
+            }
+        }
+
+        let took = resp_body["took"].as_u64().unwrap_or_default();
+        tracing::debug!(took_ms = took, hits = hits.len(), failures = failures.len(), "search completed");
+
+        Ok(SearchResult {
+            took,
+            total: serde_json::from_value(resp_body["hits"]["total"].clone()).ok(),
+            max_score: resp_body["hits"]["max_score"].as_f64(),
+            hits,
+            failures,
+            profile: resp_body.get("profile").cloned(),
+        })
+    }
+
+    pub fn sources(self) -> Vec<T> {
+        self.hits.into_iter().map(|hit| hit.source).collect()
+    }
+}
+
+fn format_profile_node(node: &Value, depth: usize, out: &mut String) {
+    let nanos = node["time_in_nanos"].as_u64().unwrap_or_default();
+    out.push_str(&format!(
+        "{}{} [{}] {:.3}ms\n",
+        "  ".repeat(depth),
+        node["type"].as_str().unwrap_or("unknown"),
+        node["description"].as_str().unwrap_or_default(),
+        nanos as f64 / 1_000_000.0
+    ));
+    for child in node["children"].as_array().into_iter().flatten() {
+        format_profile_node(child, depth + 1, out);
+    }
+}
+
+// Renders the `profile` section of a search response as an indented tree of
+// query and aggregation timings, one block per shard.
+pub fn format_profile(profile: &Value) -> String {
+    let mut out = String::new();
+    for shard in profile["shards"].as_array().into_iter().flatten() {
+        out.push_str(&format!("Shard {}\n", shard["id"].as_str().unwrap_or("unknown")));
+        for search in shard["searches"].as_array().into_iter().flatten() {
+            out.push_str("  Query:\n");
+            for query in search["query"].as_array().into_iter().flatten() {
+                format_profile_node(query, 2, &mut out);
+            }
+        }
+        if let Some(aggregations) = shard["aggregations"].as_array().filter(|aggregations| !aggregations.is_empty()) {
+            out.push_str("  Aggregations:\n");
+            for aggregation in aggregations {
+                format_profile_node(aggregation, 2, &mut out);
+            }
+        }
+    }
+    out
+}
+
+struct SearchAllState<T> {
+    pit: PitCleanup,
+    buffer: VecDeque<Result<Hit<T>, ElSearchError>>,
+    search_after: Option<Value>,
+    finished: bool,
+}
+
+// search_all owns paging: it sets size, pit and search_after itself and sorts on
+// `_shard_doc` after any sort the caller asked for. Every other key of the body is kept.
+fn search_all_body(query: &Value) -> Result<Value, ElSearchError> {
+    let mut body = to_search_body(query)?;
+    for key in ["size", "from", "pit", "search_after"] {
+        if body.get(key).is_some() {
+            return Err(ElSearchError::InvalidQuery(format!("search_all pages through the index itself, remove \"{}\" from the body", key)));
+        }
+    }
+
+    let mut sort = match body.get("sort") {
+        None => Vec::new(),
+        Some(Value::Array(keys)) => keys.clone(),
+        Some(key) => vec![key.clone()],
+    };
+    sort.push(json!({ "_shard_doc": "asc" }));
+    body["sort"] = Value::Array(sort);
+    Ok(body)
+}
+
+impl ElSearch {
+    #[instrument(skip_all, fields(index = tracing::field::Empty))]
+    pub async fn search_typed<T: DeserializeOwned>(&self, index_name: impl Into<IndexSelection>, body: &Value) -> Result<SearchResult<T>, ElSearchError> {
+        let selection = index_name.into();
+        tracing::Span::current().record("index", tracing::field::display(&selection));
+        let resp_body = read_json::<Value>(self.search(selection, body).await?).await?;
+        SearchResult::from_response_body(resp_body)
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn search_paginated<T: DeserializeOwned>(&self, index_name: &str, query: &Value, from: i64, size: i64) -> Result<Page<T>, ElSearchError> {
+        if from < 0 || size < 0 {
+            return Err(ElSearchError::InvalidArgument(format!("from ({}) and size ({}) must not be negative", from, size)));
+        }
+
+        let mut body = query.clone();
+        let Some(fields) = body.as_object_mut() else {
+            return Err(ElSearchError::InvalidArgument("search body must be a JSON object".to_string()));
+        };
+        fields.insert("from".to_string(), Value::from(from));
+        fields.insert("size".to_string(), Value::from(size));
+
+        let result = self.search_typed::<T>(index_name, &body).await?;
+        let total = result.total.as_ref().map(|total| total.value as i64).unwrap_or_default();
+        // Hits that fail to deserialize are dropped from `items` but still occupy their place
+        // in the page, so they count towards the position of the next page.
+        let returned = (result.hits.len() + result.failures.len()) as i64;
+        let items = result.sources();
+        let has_more = from + returned < total;
+
+        Ok(Page { items, total, has_more })
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn count_documents(&self, index_name: &str, query: Option<&Value>) -> Result<i64, ElSearchError> {
+        let body = query.cloned().unwrap_or_else(|| json!({ "query": { "match_all": {} } }));
+        let call = Call::new("count_documents", index_name);
+        let response = call
+            .send(Some(&body), self.client
+                .count(CountParts::Index(&[index_name]))
+                .body(&body)
+                .send())
+            .await?;
+        if response.status_code() == StatusCode::NOT_FOUND {
+            return Err(ElSearchError::IndexNotFound(index_name.to_string()));
+        }
+
+        let resp_body = call.ensure_success(response).await?.json::<Value>().await?;
+        resp_body["count"]
+            .as_i64()
+            .ok_or_else(|| ElSearchError::InvalidResponse("count response has no count".to_string()))
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn count(&self, index_name: &str, query: Option<&Value>) -> Result<u64, ElSearchError> {
+        let count = self.count_documents(index_name, query).await?;
+        Ok(count.max(0) as u64)
+    }
+
+    // Stops collecting on every shard after the first match and fetches no hits.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn exists_by_query(&self, index_name: &str, query: &Value) -> Result<bool, ElSearchError> {
+        let call = Call::new("exists_by_query", index_name);
+        let response = call
+            .send(Some(query), self.client
+                .search(SearchParts::Index(&[index_name]))
+                .size(0)
+                .terminate_after(1)
+                .body(query)
+                .send())
+            .await?;
+        if response.status_code() == StatusCode::NOT_FOUND {
+            return Err(ElSearchError::IndexNotFound(index_name.to_string()));
+        }
+
+        let resp_body = call.ensure_success(response).await?.json::<Value>().await?;
+        Ok(resp_body["hits"]["total"]["value"].as_u64().unwrap_or_default() > 0)
+    }
+
+    // The Fleet API expects one checkpoint per shard in shard order; shards without an entry get -1, i.e. no wait.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn fleet_search<T: DeserializeOwned>(&self, index_name: &str, query: &Value, wait_for_checkpoints: HashMap<u32, i64>, allow_partial_search_results: bool) -> Result<SearchResult<T>, ElSearchError> {
+        let shard_count = wait_for_checkpoints.keys().max().map(|shard| *shard + 1).unwrap_or_default();
+        let checkpoints = (0..shard_count)
+            .map(|shard| wait_for_checkpoints.get(&shard).copied().unwrap_or(-1).to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+
+        let path = format!("/{}/_fleet/_fleet_search", index_name);
+        let query_string = [
+            ("wait_for_checkpoints", checkpoints),
+            ("allow_partial_search_results", allow_partial_search_results.to_string()),
+        ];
+
+        let response = Call::new("fleet_search", index_name)
+            .send_checked(Some(query), self.client
+                .send(Method::Post, &path, HeaderMap::new(), Some(&query_string), Some(JsonBody::new(query.clone())), None))
+            .await?;
+        let resp_body = response.json::<Value>().await?;
+
+        SearchResult::from_response_body(resp_body)
+    }
+
+    // `query` is a search body or a bare query clause; anything else ends the stream with `InvalidQuery`.
+    pub fn search_all<'a, T: DeserializeOwned + 'a>(&'a self, index_name: &'a str, query: &'a Value, page_size: usize) -> impl Stream<Item = Result<Hit<T>, ElSearchError>> + 'a {
+        let state = SearchAllState {
+            pit: PitCleanup::new(&self.client),
+            buffer: VecDeque::new(),
+            search_after: None,
+            finished: false,
+        };
+
+        stream::unfold(state, move |mut state| async move {
+            loop {
+                if let Some(item) = state.buffer.pop_front() {
+                    return Some((item, state));
+                }
+                if state.finished {
+                    return None;
+                }
+
+                if let Err(err) = self.next_search_all_page(index_name, query, page_size.max(1), &mut state).await {
+                    state.finished = true;
+                    let _ = state.pit.close().await;
+                    return Some((Err(err), state));
+                }
+            }
+        })
+    }
+
+    async fn next_search_all_page<T: DeserializeOwned>(&self, index_name: &str, query: &Value, page_size: usize, state: &mut SearchAllState<T>) -> Result<(), ElSearchError> {
+        let mut body = search_all_body(query)?;
+        if state.pit.id.is_none() {
+            state.pit.id = Some(self.open_pit(index_name, DEFAULT_KEEP_ALIVE).await?);
+        }
+
+        body["size"] = json!(page_size);
+        body["pit"] = json!({
+            "id": state.pit.id,
+            "keep_alive": DEFAULT_KEEP_ALIVE
+        });
+        if let Some(search_after) = &state.search_after {
+            body["search_after"] = search_after.clone();
+        }
+
+        let response = Call::new("search_all", index_name)
+            .send_checked(Some(&body), self.client
+                .search(SearchParts::None)
+                .body(&body)
+                .send())
+            .await?;
+        let resp_body = response.json::<Value>().await?;
+
+        if let Some(pit_id) = resp_body["pit_id"].as_str() {
+            state.pit.id = Some(pit_id.to_string());
+        }
+
+        let raw_hits = resp_body["hits"]["hits"].as_array();
+        let page_len = raw_hits.map(Vec::len).unwrap_or_default();
+        state.search_after = raw_hits
+            .and_then(|hits| hits.last())
+            .map(|hit| hit["sort"].clone());
+
+        let result = SearchResult::<T>::from_response_body(resp_body)?;
+        state.buffer.extend(result.hits.into_iter().map(Ok));
+        state.buffer.extend(result.failures.into_iter().map(|failure| {
+            Err(ElSearchError::InvalidResponse(format!("document {} could not be deserialized: {}", failure.id, failure.reason)))
+        }));
+
+        if page_len < page_size {
+            state.finished = true;
+            state.pit.close().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{ pin_mut, StreamExt };
+    use serde_json::json;
+    use std::collections::HashSet;
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, Request, Respond, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::{ mock_client, received };
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Named {
+        name: String,
+    }
+
+    #[tokio::test]
+    async fn search_typed_parses_hits_and_totals() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "took": 4,
+                "hits": {
+                    "total": { "value": 12, "relation": "eq" },
+                    "max_score": 1.5,
+                    "hits": [
+                        { "_id": "a", "_score": 1.5, "_source": { "name": "Laptop" } },
+                        { "_id": "b", "_score": 0.5, "_source": { "name": "Mouse" } }
+                    ]
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let result = mock_client(&server)
+            .search_typed::<Named>("products", &json!({ "query": { "match_all": {} } }))
+            .await
+            .unwrap();
+
+        assert_eq!(result.took, 4);
+        let total = result.total.unwrap();
+        assert_eq!((total.value, total.relation.as_str()), (12, "eq"));
+        assert_eq!(result.max_score, Some(1.5));
+        let hits = result.hits.iter().map(|hit| (hit.id.as_str(), hit.source.name.as_str())).collect::<Vec<_>>();
+        assert_eq!(hits, vec![("a", "Laptop"), ("b", "Mouse")]);
+    }
+
+    #[tokio::test]
+    async fn non_success_status_is_an_api_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": { "type": "parsing_exception", "reason": "unknown query [matchh]" },
+                "status": 400
+            })))
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server)
+            .search_typed::<Value>("products", &json!({ "query": { "matchh": {} } }))
+            .await
+            .unwrap_err();
+
+        match err {
+            ElSearchError::Api { status, body } => {
+                assert_eq!(status, 400);
+                assert!(body.contains("parsing_exception"), "{}", body);
+            }
+            other => panic!("expected an api error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn unparseable_body_reports_a_snippet() {
+        let server = MockServer::start().await;
+        let page = format!("<html><body>gateway says no{}</body></html>", " ".repeat(500));
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(page))
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server)
+            .search_typed::<Value>("products", &json!({}))
+            .await
+            .unwrap_err();
+
+        match err {
+            ElSearchError::InvalidResponse(reason) => {
+                assert!(reason.contains("<html><body>gateway says no"), "{}", reason);
+                assert!(reason.ends_with("..."), "long bodies are truncated: {}", reason);
+                assert!(!reason.contains("</html>"), "{}", reason);
+            }
+            other => panic!("expected an invalid response error, got {:?}", other),
+        }
+    }
+
+    // 25,000 documents numbered by their sort value, served page by page after the
+    // request's `search_after` like a point-in-time search over `_shard_doc`.
+    struct ShardDocPages;
+
+    impl Respond for ShardDocPages {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            const DOCUMENTS: u64 = 25_000;
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let size = body["size"].as_u64().unwrap();
+            let start = body["search_after"][0].as_u64().map_or(0, |last| last + 1);
+            let end = (start + size).min(DOCUMENTS);
+
+            let hits = (start..end)
+                .map(|n| json!({ "_id": format!("doc-{}", n), "_source": { "n": n }, "sort": [n] }))
+                .collect::<Vec<_>>();
+            ResponseTemplate::new(200).set_body_json(json!({
+                "pit_id": "pit-1",
+                "hits": { "total": { "value": DOCUMENTS, "relation": "eq" }, "hits": hits }
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn search_all_returns_every_document_exactly_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_pit"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "pit-1" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/_search"))
+            .respond_with(ShardDocPages)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/_pit"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "succeeded": true })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let es = mock_client(&server);
+        let query = json!({});
+        let hits = es.search_all::<Value>("products", &query, 1000);
+        pin_mut!(hits);
+        let mut seen = HashSet::new();
+        while let Some(hit) = hits.next().await {
+            let hit = hit.unwrap();
+            assert!(seen.insert(hit.id.clone()), "{} was returned twice", hit.id);
+        }
+
+        assert_eq!(seen.len(), 25_000);
+        assert!((0..25_000).all(|n| seen.contains(&format!("doc-{}", n))));
+        // 25 full pages and the empty page that ends the iteration.
+        let searches = received(&server).await.iter().filter(|request| request.url.path() == "/_search").count();
+        assert_eq!(searches, 26);
+    }
+
+    #[tokio::test]
+    async fn search_all_forwards_a_bare_clause_into_the_pit_search() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_pit"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "pit-1" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "pit_id": "pit-1",
+                "hits": { "total": { "value": 0, "relation": "eq" }, "hits": [] }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/_pit"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "succeeded": true })))
+            .mount(&server)
+            .await;
+
+        let es = mock_client(&server);
+        let clause = json!({ "term": { "brand": "acme" } });
+        let body = json!({ "query": clause, "_source": ["name"], "sort": [{ "price": "desc" }] });
+        for query in [&clause, &body] {
+            let hits = es.search_all::<Value>("products", query, 100);
+            pin_mut!(hits);
+            while let Some(hit) = hits.next().await {
+                hit.unwrap();
+            }
+        }
+
+        let searches = received(&server).await
+            .into_iter()
+            .filter(|request| request.url.path() == "/_search")
+            .map(|request| serde_json::from_slice::<Value>(&request.body).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(searches.len(), 2);
+        assert_eq!(searches[0]["query"], clause);
+        assert_eq!(searches[0]["sort"], json!([{ "_shard_doc": "asc" }]));
+        assert_eq!(searches[0]["pit"]["id"], "pit-1");
+        assert_eq!(searches[1]["query"], clause);
+        assert_eq!(searches[1]["_source"], json!(["name"]));
+        assert_eq!(searches[1]["sort"], json!([{ "price": "desc" }, { "_shard_doc": "asc" }]));
+    }
+
+    #[tokio::test]
+    async fn search_all_rejects_a_query_it_cannot_interpret() {
+        let server = MockServer::start().await;
+        let es = mock_client(&server);
+
+        for query in [json!(["term"]), json!({ "term": { "brand": "acme" }, "match": { "name": "x" } }), json!({ "query": {}, "from": 10 })] {
+            let hits = es.search_all::<Value>("products", &query, 100);
+            pin_mut!(hits);
+            match hits.next().await {
+                Some(Err(ElSearchError::InvalidQuery(_))) => {}
+                other => panic!("expected an invalid query error for {}, got {:?}", query, other),
+            }
+            assert!(hits.next().await.is_none());
+        }
+        assert!(received(&server).await.is_empty(), "no point in time is opened for a bad query");
+    }
+
+    #[tokio::test]
+    async fn count_and_exists_by_query_read_the_cheap_endpoints() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_count"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "count": 42 })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "terminated_early": true,
+                "hits": { "total": { "value": 1, "relation": "eq" }, "hits": [] }
+            })))
+            .mount(&server)
+            .await;
+
+        let es = mock_client(&server);
+        let query = json!({ "query": { "term": { "brand": "acme" } } });
+        assert_eq!(es.count("products", None).await.unwrap(), 42);
+        assert_eq!(es.count("products", Some(&query)).await.unwrap(), 42);
+        assert!(es.exists_by_query("products", &query).await.unwrap());
+
+        let requests = received(&server).await;
+        let bodies = requests.iter().map(|request| serde_json::from_slice::<Value>(&request.body).unwrap()).collect::<Vec<_>>();
+        assert_eq!(bodies[0], json!({ "query": { "match_all": {} } }));
+        assert_eq!(bodies[1], query);
+        let exists = &requests[2];
+        let params = exists.url.query_pairs().map(|(key, value)| (key.into_owned(), value.into_owned())).collect::<HashMap<_, _>>();
+        assert_eq!(params.get("size").map(String::as_str), Some("0"));
+        assert_eq!(params.get("terminate_after").map(String::as_str), Some("1"));
+    }
+
+    #[tokio::test]
+    async fn no_match_does_not_exist() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "hits": { "total": { "value": 0, "relation": "eq" }, "hits": [] }
+            })))
+            .mount(&server)
+            .await;
+
+        assert!(!mock_client(&server).exists_by_query("products", &json!({})).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn counting_a_missing_index_is_index_not_found() {
+        let server = MockServer::start().await;
+        let missing = ResponseTemplate::new(404).set_body_json(json!({
+            "error": { "type": "index_not_found_exception", "reason": "no such index [gone]", "index": "gone" },
+            "status": 404
+        }));
+        Mock::given(method("POST"))
+            .and(path("/gone/_count"))
+            .respond_with(missing.clone())
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/gone/_search"))
+            .respond_with(missing)
+            .mount(&server)
+            .await;
+
+        let es = mock_client(&server);
+        let err = es.count("gone", None).await.unwrap_err();
+        assert!(matches!(&err, ElSearchError::IndexNotFound(index) if index == "gone"), "{:?}", err);
+        let err = es.exists_by_query("gone", &json!({})).await.unwrap_err();
+        assert!(matches!(&err, ElSearchError::IndexNotFound(index) if index == "gone"), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn undeserializable_hits_still_count_towards_has_more() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/products/_search"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "hits": {
+                    "total": { "value": 4, "relation": "eq" },
+                    "hits": [
+                        { "_id": "c", "_source": { "name": "Keyboard" } },
+                        { "_id": "d", "_source": { "title": "no name field" } }
+                    ]
+                }
+            })))
+            .mount(&server)
+            .await;
+
+        let page = mock_client(&server).search_paginated::<Named>("products", &json!({}), 2, 2).await.unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].name, "Keyboard");
+        assert!(!page.has_more);
+    }
+}
@@ -0,0 +1,194 @@
+use elasticsearch::http::{ headers::HeaderMap, request::JsonBody, response::Response, Method };
+use serde::Deserialize;
+use serde_json::{ json, Value };
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ShutdownType {
+    Remove,
+    Restart,
+    Sigterm,
+    Replace,
+    // A type added by a newer Elasticsearch; it can be read back but not requested.
+    #[serde(other)]
+    Unknown,
+}
+
+impl ShutdownType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShutdownType::Remove => "remove",
+            ShutdownType::Restart => "restart",
+            ShutdownType::Sigterm => "sigterm",
+            ShutdownType::Replace => "replace",
+            ShutdownType::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ShardMigrationStatus {
+    NotStarted,
+    InProgress,
+    Stalled,
+    Complete,
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeShutdownStatus {
+    pub node_id: String,
+    pub type_: ShutdownType,
+    pub status: ShardMigrationStatus,
+    pub shards_remaining: u32,
+    // Only set for `ShutdownType::Replace`.
+    pub target_node_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ShutdownStatusResponse {
+    nodes: Vec<ShutdownNode>,
+}
+
+#[derive(Deserialize)]
+struct ShutdownNode {
+    node_id: String,
+    #[serde(rename = "type")]
+    type_: ShutdownType,
+    #[serde(default)]
+    target_node_name: Option<String>,
+    shard_migration: ShardMigration,
+}
+
+#[derive(Deserialize)]
+struct ShardMigration {
+    status: ShardMigrationStatus,
+    #[serde(default)]
+    shard_migrations_remaining: u32,
+}
+
+impl ElSearch {
+    // A replacement names the node taking over, so it goes through `prepare_node_replacement`.
+    #[instrument(skip_all)]
+    pub async fn prepare_node_shutdown(&self, node_id: &str, type_: ShutdownType, reason: &str) -> Result<Response, ElSearchError> {
+        if matches!(type_, ShutdownType::Replace | ShutdownType::Unknown) {
+            return Err(ElSearchError::InvalidArgument(format!("a {} shutdown cannot be prepared with prepare_node_shutdown", type_.as_str())));
+        }
+        let body = json!({
+            "type": type_.as_str(),
+            "reason": reason
+        });
+        self.put_node_shutdown("prepare_node_shutdown", node_id, body).await
+    }
+
+    #[instrument(skip_all)]
+    pub async fn prepare_node_replacement(&self, node_id: &str, target_node_name: &str, reason: &str) -> Result<Response, ElSearchError> {
+        let body = json!({
+            "type": ShutdownType::Replace.as_str(),
+            "target_node_name": target_node_name,
+            "reason": reason
+        });
+        self.put_node_shutdown("prepare_node_replacement", node_id, body).await
+    }
+
+    async fn put_node_shutdown(&self, op: &'static str, node_id: &str, body: Value) -> Result<Response, ElSearchError> {
+        let path = format!("/_nodes/{}/shutdown", node_id);
+        Call::cluster(op)
+            .send_checked(Some(&body), self.client
+                .send(Method::Put, &path, HeaderMap::new(), Option::<&Value>::None, Some(JsonBody::new(body.clone())), None))
+            .await
+    }
+
+    #[instrument(skip_all)]
+    pub async fn cancel_node_shutdown(&self, node_id: &str) -> Result<Response, ElSearchError> {
+        let path = format!("/_nodes/{}/shutdown", node_id);
+
+        Call::cluster("cancel_node_shutdown")
+            .send_checked(None, self.client
+                .send(Method::Delete, &path, HeaderMap::new(), Option::<&Value>::None, Option::<JsonBody<Value>>::None, None))
+            .await
+    }
+
+    #[instrument(skip_all)]
+    pub async fn node_shutdown_status(&self, node_id: Option<&str>) -> Result<Vec<NodeShutdownStatus>, ElSearchError> {
+        let path = match node_id {
+            Some(node_id) => format!("/_nodes/{}/shutdown", node_id),
+            None => "/_nodes/shutdown".to_string(),
+        };
+
+        let response = Call::cluster("node_shutdown_status")
+            .send_checked(None, self.client
+                .send(Method::Get, &path, HeaderMap::new(), Option::<&Value>::None, Option::<JsonBody<Value>>::None, None))
+            .await?;
+        let resp_body = response.json::<ShutdownStatusResponse>().await?;
+
+        Ok(resp_body.nodes
+            .into_iter()
+            .map(|node| NodeShutdownStatus {
+                node_id: node.node_id,
+                type_: node.type_,
+                status: node.shard_migration.status,
+                shards_remaining: node.shard_migration.shard_migrations_remaining,
+                target_node_name: node.target_node_name,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::{ mock_client, received };
+
+    #[tokio::test]
+    async fn shutdown_status_reads_every_type() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/_nodes/shutdown"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "nodes": [
+                { "node_id": "n1", "type": "RESTART", "shard_migration": { "status": "COMPLETE" } },
+                { "node_id": "n2", "type": "REPLACE", "target_node_name": "node-4",
+                  "shard_migration": { "status": "IN_PROGRESS", "shard_migrations_remaining": 3 } },
+                { "node_id": "n3", "type": "HIBERNATE", "shard_migration": { "status": "NOT_STARTED" } }
+            ] })))
+            .mount(&server)
+            .await;
+
+        let nodes = mock_client(&server).node_shutdown_status(None).await.unwrap();
+        let summary = nodes
+            .iter()
+            .map(|node| (node.node_id.as_str(), node.type_, node.status, node.shards_remaining, node.target_node_name.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, vec![
+            ("n1", ShutdownType::Restart, ShardMigrationStatus::Complete, 0, None),
+            ("n2", ShutdownType::Replace, ShardMigrationStatus::InProgress, 3, Some("node-4")),
+            ("n3", ShutdownType::Unknown, ShardMigrationStatus::NotStarted, 0, None),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn a_replacement_names_its_target_node() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/_nodes/n2/shutdown"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "acknowledged": true })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let es = mock_client(&server);
+        es.prepare_node_replacement("n2", "node-4", "disk swap").await.unwrap();
+        let err = es.prepare_node_shutdown("n2", ShutdownType::Replace, "disk swap").await.unwrap_err();
+        assert!(matches!(err, ElSearchError::InvalidArgument(_)), "{:?}", err);
+
+        let body: Value = serde_json::from_slice(&received(&server).await[0].body).unwrap();
+        assert_eq!(body, json!({ "type": "replace", "target_node_name": "node-4", "reason": "disk swap" }));
+    }
+}
@@ -0,0 +1,53 @@
+use elasticsearch::snapshot::SnapshotGetParts;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+
+#[derive(Debug, Deserialize)]
+pub struct SnapshotRepository {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub repository_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SnapshotInfo {
+    pub snapshot: String,
+    pub indices: Vec<String>,
+    pub state: String,
+    pub start_time: String,
+    pub duration: String,
+}
+
+impl ElSearch {
+    #[instrument(skip_all)]
+    pub async fn list_snapshot_repositories(&self) -> Result<Vec<SnapshotRepository>, ElSearchError> {
+        let response = Call::cluster("list_snapshot_repositories")
+            .send_checked(None, self.client
+                .cat()
+                .repositories()
+                .format("json")
+                .send())
+            .await?;
+        Ok(response.json::<Vec<SnapshotRepository>>().await?)
+    }
+
+    #[instrument(skip_all)]
+    pub async fn list_snapshots(&self, repository: &str) -> Result<Vec<SnapshotInfo>, ElSearchError> {
+        let response = Call::cluster("list_snapshots")
+            .send_checked(None, self.client
+                .snapshot()
+                .get(SnapshotGetParts::RepositorySnapshot(repository, &["_all"]))
+                .human(true)
+                .send())
+            .await?;
+        let resp_body = response.json::<Value>().await?;
+
+        let snapshots = resp_body.get("snapshots").cloned().unwrap_or_else(|| Value::Array(Vec::new()));
+        Ok(serde_json::from_value(snapshots)?)
+    }
+}
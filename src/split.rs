@@ -0,0 +1,217 @@
+use elasticsearch::http::response::Response;
+use serde::de::DeserializeOwned;
+use serde_json::{ json, Value };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use tracing::instrument;
+
+use crate::client::ElSearch;
+use crate::documents::WriteOutcome;
+use crate::error::ElSearchError;
+use crate::logging::Call;
+use crate::search::SearchResult;
+
+#[derive(Debug, Clone)]
+pub struct SplitIndexHandle {
+    pub read_alias: String,
+    pub write_alias: String,
+}
+
+impl SplitIndexHandle {
+    pub fn new(read_alias: &str, write_alias: &str) -> Self {
+        SplitIndexHandle {
+            read_alias: read_alias.to_string(),
+            write_alias: write_alias.to_string(),
+        }
+    }
+}
+
+pub struct SplitIndex<'a> {
+    es: &'a ElSearch,
+    handle: SplitIndexHandle,
+    read_your_writes: bool,
+    written: AtomicBool,
+}
+
+impl ElSearch {
+    pub fn split_index(&self, handle: SplitIndexHandle) -> SplitIndex<'_> {
+        SplitIndex {
+            es: self,
+            handle,
+            read_your_writes: false,
+            written: AtomicBool::new(false),
+        }
+    }
+
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn create_split_aliases(&self, index_name: &str, handle: &SplitIndexHandle) -> Result<Response, ElSearchError> {
+        let body = json!({
+            "actions": [
+                { "add": { "index": index_name, "alias": handle.read_alias } },
+                { "add": { "index": index_name, "alias": handle.write_alias, "is_write_index": true } }
+            ]
+        });
+
+        Call::new("create_split_aliases", index_name)
+            .send_checked(Some(&body), self.client
+                .indices()
+                .update_aliases()
+                .body(&body)
+                .send())
+            .await
+    }
+}
+
+impl SplitIndex<'_> {
+    pub fn handle(&self) -> &SplitIndexHandle {
+        &self.handle
+    }
+
+    pub fn read_your_writes(mut self, enabled: bool) -> Self {
+        self.read_your_writes = enabled;
+        self
+    }
+
+    pub fn read_target(&self) -> &str {
+        if self.read_your_writes && self.written.load(Ordering::Relaxed) {
+            &self.handle.write_alias
+        } else {
+            &self.handle.read_alias
+        }
+    }
+
+    pub fn write_target(&self) -> &str {
+        &self.handle.write_alias
+    }
+
+    pub async fn search(&self, body: &Value) -> Result<Response, ElSearchError> {
+        self.es.search(self.read_target(), body).await
+    }
+
+    pub async fn search_typed<T: DeserializeOwned>(&self, body: &Value) -> Result<SearchResult<T>, ElSearchError> {
+        self.es.search_typed(self.read_target(), body).await
+    }
+
+    pub async fn search_read_through<T: DeserializeOwned>(&self, body: &Value) -> Result<SearchResult<T>, ElSearchError> {
+        self.es.search_typed(self.write_target(), body).await
+    }
+
+    pub async fn get_document<T: DeserializeOwned>(&self, id: &str) -> Result<Option<T>, ElSearchError> {
+        self.es.get_document(self.read_target(), id).await
+    }
+
+    pub async fn add_document(&self, body: &Value) -> Result<Response, ElSearchError> {
+        let response = self.es.add_document(self.write_target(), body).await?;
+        self.written.store(true, Ordering::Relaxed);
+        Ok(response)
+    }
+
+    pub async fn update_document(&self, id: &str, partial: &Value) -> Result<WriteOutcome, ElSearchError> {
+        let outcome = self.es.update_document(self.write_target(), id, partial).await?;
+        self.written.store(true, Ordering::Relaxed);
+        Ok(outcome)
+    }
+
+    pub async fn delete_document(&self, id: &str) -> Result<WriteOutcome, ElSearchError> {
+        let outcome = self.es.delete_document(self.write_target(), id).await?;
+        self.written.store(true, Ordering::Relaxed);
+        Ok(outcome)
+    }
+
+    pub async fn bulk_create(&self, operations: Vec<Value>) -> Result<Response, ElSearchError> {
+        let response = self.es.bulk_create_by_index(self.write_target(), operations).await?;
+        self.written.store(true, Ordering::Relaxed);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{ matchers::{ method, path }, Mock, MockServer, ResponseTemplate };
+
+    use super::*;
+    use crate::test_support::{ mock_client, received };
+
+    fn write_outcome(result: &str) -> Value {
+        json!({ "_id": "a", "_version": 2, "result": result, "_seq_no": 1, "_primary_term": 1 })
+    }
+
+    async fn split_server() -> MockServer {
+        let server = MockServer::start().await;
+        for alias in ["products-read", "products-write"] {
+            Mock::given(method("GET"))
+                .and(path(format!("/{}/_doc/a", alias)))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "_id": "a", "_seq_no": 1, "_primary_term": 1, "found": true, "_source": { "name": alias }
+                })))
+                .mount(&server)
+                .await;
+            Mock::given(method("POST"))
+                .and(path(format!("/{}/_search", alias)))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "hits": { "total": { "value": 0, "relation": "eq" }, "hits": [] }
+                })))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path("/products-write/_update/a"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(write_outcome("updated")))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/products-write/_doc/a"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(write_outcome("deleted")))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn paths(requests: &[wiremock::Request]) -> Vec<String> {
+        requests.iter().map(|request| format!("{} {}", request.method, request.url.path())).collect()
+    }
+
+    #[tokio::test]
+    async fn reads_use_the_read_alias_and_writes_the_write_alias() {
+        let server = split_server().await;
+        let es = mock_client(&server);
+        let split = es.split_index(SplitIndexHandle::new("products-read", "products-write"));
+
+        let doc = split.get_document::<Value>("a").await.unwrap().unwrap();
+        assert_eq!(doc["name"], "products-read");
+        split.search_typed::<Value>(&json!({})).await.unwrap();
+        split.update_document("a", &json!({ "price": 10 })).await.unwrap();
+        split.delete_document("a").await.unwrap();
+        // Without read-your-writes, reads stay on the read alias after a write.
+        split.get_document::<Value>("a").await.unwrap();
+
+        assert_eq!(paths(&received(&server).await), vec![
+            "GET /products-read/_doc/a",
+            "POST /products-read/_search",
+            "POST /products-write/_update/a",
+            "DELETE /products-write/_doc/a",
+            "GET /products-read/_doc/a",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn read_your_writes_switches_reads_to_the_write_alias_after_a_write() {
+        let server = split_server().await;
+        let es = mock_client(&server);
+        let split = es
+            .split_index(SplitIndexHandle::new("products-read", "products-write"))
+            .read_your_writes(true);
+
+        split.get_document::<Value>("a").await.unwrap();
+        split.update_document("a", &json!({ "price": 10 })).await.unwrap();
+        let doc = split.get_document::<Value>("a").await.unwrap().unwrap();
+        assert_eq!(doc["name"], "products-write");
+        split.search_typed::<Value>(&json!({})).await.unwrap();
+
+        assert_eq!(paths(&received(&server).await), vec![
+            "GET /products-read/_doc/a",
+            "POST /products-write/_update/a",
+            "GET /products-write/_doc/a",
+            "POST /products-write/_search",
+        ]);
+    }
+}
@@ -0,0 +1,91 @@
+use serde_json::Value;
+use std::io::{ self, Write };
+use std::path::PathBuf;
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+use tracing_subscriber::fmt::format::FmtSpan;
+use wiremock::{ MockServer, Request, Respond, ResponseTemplate };
+
+use crate::client::ElSearch;
+use crate::fixtures::{ bulk_response, ItemOutcome };
+use crate::retry::RetryPolicy;
+
+// Retries without waiting so tests that exercise 429/503 handling stay fast.
+pub(crate) fn fast_retry_policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(5),
+        jitter: false,
+    }
+}
+
+pub(crate) fn mock_client(server: &MockServer) -> ElSearch {
+    ElSearch::new_from_localhost(&server.uri())
+        .expect("mock server uri is a valid url")
+        .with_retry_policy(fast_retry_policy())
+}
+
+pub(crate) async fn received(server: &MockServer) -> Vec<Request> {
+    server.received_requests().await.expect("request recording is enabled")
+}
+
+pub(crate) fn ndjson_lines(request: &Request) -> Vec<Value> {
+    std::str::from_utf8(&request.body)
+        .expect("bulk body is utf-8")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("every bulk line is json"))
+        .collect()
+}
+
+// Collects everything a `fmt` subscriber writes while the returned guard is alive.
+#[derive(Clone, Default)]
+pub(crate) struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl CapturedLogs {
+    // Formatted like the example binary's output, including a line when each span closes.
+    pub(crate) fn install(&self) -> tracing::subscriber::DefaultGuard {
+        let logs = self.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_ansi(false)
+            .without_time()
+            .with_writer(move || logs.clone())
+            .finish();
+        tracing::subscriber::set_default(subscriber)
+    }
+
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let bytes = self.0.lock().expect("log buffer is not poisoned");
+        String::from_utf8_lossy(&bytes).lines().map(String::from).collect()
+    }
+}
+
+impl Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().expect("log buffer is not poisoned").extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Answers a bulk request of create/index actions with one created item per document.
+pub(crate) struct AcknowledgeBulk;
+
+impl Respond for AcknowledgeBulk {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let documents = ndjson_lines(request).len() / 2;
+        ResponseTemplate::new(200).set_body_json(bulk_response(vec![ItemOutcome::Created; documents]))
+    }
+}
+
+// Writes `contents` to a file in the temp directory that is unique to this test process.
+pub(crate) fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("es01-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).expect("temp dir is writable");
+    path
+}
@@ -0,0 +1,125 @@
+use elasticsearch::http::response::Response;
+use serde::{ de::DeserializeOwned, Serialize };
+use serde_json::Value;
+use tracing::instrument;
+
+use crate::bulk::BulkReport;
+use crate::client::ElSearch;
+use crate::error::ElSearchError;
+
+// Deserializes `document` into `T`, naming the offending field (e.g. "price" or
+// "variants[2].sku") when it does not fit.
+pub fn validate_document<T: DeserializeOwned>(document: &Value, position: usize) -> Result<T, ElSearchError> {
+    serde_path_to_error::deserialize(document).map_err(|err| {
+        let reason = err.inner().to_string();
+        ElSearchError::Validation {
+            position,
+            field: field_name(&err.path().to_string(), &reason),
+            reason,
+        }
+    })
+}
+
+// A missing field is reported on the object that lacks it (path "." at the top level),
+// so the field's name is taken from serde's message and appended to that path.
+fn field_name(path: &str, reason: &str) -> String {
+    let missing = reason
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split('`').next());
+    match (path, missing) {
+        (".", Some(name)) => name.to_string(),
+        (path, Some(name)) => format!("{}.{}", path, name),
+        (path, None) => path.to_string(),
+    }
+}
+
+impl ElSearch {
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn add_typed<T: Serialize>(&self, index_name: &str, doc: &T) -> Result<Response, ElSearchError> {
+        let body = serde_json::to_value(doc)?;
+        self.add_document(index_name, &body).await
+    }
+
+    // Rejects the document locally, before any request is sent, unless it deserializes into `T`.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn add_document_checked<T: Serialize + DeserializeOwned>(&self, index_name: &str, body: &Value) -> Result<Response, ElSearchError> {
+        let doc = validate_document::<T>(body, 0)?;
+        self.add_typed(index_name, &doc).await
+    }
+
+    // Validates every document before sending anything; the first invalid one fails the
+    // whole batch with its position. Documents are indexed as re-serialized from `T`.
+    #[instrument(skip_all, fields(index = %index_name))]
+    pub async fn bulk_create_checked_as<T: Serialize + DeserializeOwned>(&self, index_name: &str, operations: Vec<Value>) -> Result<BulkReport, ElSearchError> {
+        let documents = operations
+            .iter()
+            .enumerate()
+            .map(|(position, operation)| {
+                let doc = validate_document::<T>(operation, position)?;
+                Ok(serde_json::to_value(doc)?)
+            })
+            .collect::<Result<Vec<Value>, ElSearchError>>()?;
+
+        self.bulk_create_checked(index_name, documents).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::Product;
+
+    fn product() -> Value {
+        json!({ "brand": "Acme", "category": "tools", "description": "A hammer", "name": "Hammer", "price": 9.5, "rating": 4.0 })
+    }
+
+    fn invalid_field(document: &Value) -> (String, String) {
+        match validate_document::<Product>(document, 3) {
+            Err(ElSearchError::Validation { position, field, reason }) => {
+                assert_eq!(position, 3);
+                (field, reason)
+            }
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_complete_product_is_valid() {
+        let product = validate_document::<Product>(&product(), 0).unwrap();
+        assert_eq!(product.name, "Hammer");
+    }
+
+    #[test]
+    fn an_unknown_field_is_rejected() {
+        let mut document = product();
+        document["colour"] = json!("red");
+        let (field, reason) = invalid_field(&document);
+        assert_eq!(field, "colour");
+        assert!(reason.contains("unknown field `colour`"), "{}", reason);
+    }
+
+    #[test]
+    fn a_missing_field_is_named() {
+        let mut document = product();
+        document.as_object_mut().unwrap().remove("price");
+        let (field, reason) = invalid_field(&document);
+        assert_eq!(field, "price");
+        assert!(reason.contains("missing field `price`"), "{}", reason);
+    }
+
+    #[test]
+    fn a_wrongly_typed_field_is_named() {
+        let mut document = product();
+        document["rating"] = json!("five");
+        let (field, reason) = invalid_field(&document);
+        assert_eq!(field, "rating");
+        assert!(reason.contains("invalid type"), "{}", reason);
+    }
+
+    #[test]
+    fn a_missing_nested_field_keeps_its_path() {
+        assert_eq!(field_name("variants[2]", "missing field `sku`"), "variants[2].sku");
+    }
+}